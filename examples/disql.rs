@@ -52,6 +52,8 @@ fn main() {
                 session.push(Transaction {
                     events: txn_deser.op.clone(),
                     success: txn_deser.committed,
+                    aborts: Vec::new(),
+                    time: None,
                 })
             }
 
@@ -70,22 +72,13 @@ fn main() {
     // }
 
     // let mut count = 0;
-    for (id, hist) in histories.iter().enumerate() {
-        use std::path::PathBuf;
-        let mut verifier = Verifier::new(PathBuf::from("solve_dir"));
-        verifier.model("");
-
-        let min_level = match verifier.verify(hist) {
-            Some(Consistency::RepeatableRead) => Consistency::ReadCommitted,
-            Some(Consistency::ReadAtomic) => Consistency::RepeatableRead,
-            Some(Consistency::Causal) => Consistency::ReadAtomic,
-            Some(Consistency::Prefix) => Consistency::Causal,
-            Some(Consistency::SnapshotIsolation) => Consistency::Prefix,
-            Some(Consistency::Serializable) => Consistency::SnapshotIsolation,
-            None => Consistency::Serializable,
-            _ => unreachable!(),
-        };
-        println!("{:?}", min_level);
+    for hist in histories.iter() {
+        let verifier = Verifier::new(Consistency::Serializable);
+
+        match verifier.verify(hist) {
+            Some(level) => println!("violates {}", level),
+            None => println!("{}", Consistency::Serializable),
+        }
     }
     // println!("bad histories {}/{}", count, histories.len());
 }
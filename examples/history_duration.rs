@@ -1,16 +1,29 @@
 extern crate dbcop;
 
-use dbcop::db::history::History;
+use dbcop::db::format::load_history;
 use std::env;
-use std::fs::File;
-use std::io::BufReader;
+use std::path::Path;
 
 fn main() {
-    let file = File::open(env::args().nth(1).unwrap()).unwrap();
-    let buf_reader = BufReader::new(file);
-    let hist: History = bincode::deserialize_from(buf_reader).unwrap();
+    let hist = load_history(Path::new(&env::args().nth(1).unwrap())).unwrap();
     println!(
         "{:?}",
         hist.get_duration().num_nanoseconds().unwrap() as f64 / 1_000_000_000f64
     );
+
+    // latency percentiles of committed transactions, for histories executed with timestamps
+    let mut latencies: Vec<u64> = hist
+        .get_data()
+        .iter()
+        .flatten()
+        .filter(|t| t.success)
+        .filter_map(|t| t.latency())
+        .collect();
+    latencies.sort_unstable();
+    if !latencies.is_empty() {
+        for p in [50, 90, 99] {
+            let latency = latencies[(latencies.len() - 1) * p / 100];
+            println!("p{} {}ms", p, latency as f64 / 1_000_000f64);
+        }
+    }
 }
@@ -67,6 +67,8 @@ SI_DEST=/tmp/si/
 CSV_DEST=/tmp/csv
 
 DB=postgres
+ISOLATION=repeatable-read
+INIT=lazy
 ADDR=127.0.0.1:5432
 
 COBRA_DIR="$HOME/Source/CobraVerifier"
@@ -107,7 +109,7 @@ done
 # run operations to get history
 for p in "${PARAMS[@]}"; do
   mkdir -p "$HIST_DEST/$p"
-  "$GENERATOR_DIR/target/release/dbcop" run $ADDR --db $DB --dir "/tmp/generate/$p" --out "$HIST_DEST/$p" >/dev/null
+  "$GENERATOR_DIR/target/release/dbcop" run $ADDR --db $DB --isolation $ISOLATION --init $INIT --dir "/tmp/generate/$p" --out "$HIST_DEST/$p" >/dev/null
 done
 
 # verify with si
@@ -0,0 +1,63 @@
+//! Why a driver error aborted a transaction attempt.
+
+use std::fmt;
+
+use crate::db::history::AbortReason;
+
+use postgres::error::SqlState;
+
+use super::antidote_pb;
+
+pub fn postgres(e: &postgres::Error) -> AbortReason {
+    if e.is_closed() {
+        return AbortReason::ConnectionLost;
+    }
+    match e.code() {
+        Some(code) if *code == SqlState::T_R_SERIALIZATION_FAILURE => AbortReason::Serialization,
+        Some(code) if *code == SqlState::T_R_DEADLOCK_DETECTED => AbortReason::Deadlock,
+        Some(code) if *code == SqlState::QUERY_CANCELED || *code == SqlState::LOCK_NOT_AVAILABLE => {
+            AbortReason::Timeout
+        }
+        _ => AbortReason::Other,
+    }
+}
+
+pub fn mysql(e: &mysql::Error) -> AbortReason {
+    match e {
+        mysql::Error::IoError(_) => AbortReason::ConnectionLost,
+        mysql::Error::MySqlError(e) => match e.code {
+            // ER_LOCK_DEADLOCK, Galera also reports certification failures with it
+            1213 => AbortReason::Deadlock,
+            // ER_LOCK_WAIT_TIMEOUT, ER_QUERY_TIMEOUT, ER_STATEMENT_TIMEOUT (MariaDB)
+            1205 | 3024 | 1969 => AbortReason::Timeout,
+            // TiDB write conflicts
+            8002 | 8022 | 9007 => AbortReason::Serialization,
+            _ => AbortReason::Other,
+        },
+        _ => AbortReason::Other,
+    }
+}
+
+/// DGraph only reports errors as text, conflicting commits are reported as aborted.
+pub fn dgraph<E: fmt::Display>(e: &E) -> AbortReason {
+    let msg = format!("{:#}", e).to_lowercase();
+    if msg.contains("aborted") {
+        AbortReason::Serialization
+    } else if msg.contains("deadline") || msg.contains("timed out") {
+        AbortReason::Timeout
+    } else if msg.contains("transport") || msg.contains("unavailable") {
+        AbortReason::ConnectionLost
+    } else {
+        AbortReason::Other
+    }
+}
+
+pub fn antidote(e: &antidote_pb::Error) -> AbortReason {
+    match e {
+        antidote_pb::Error::Io(_) => AbortReason::ConnectionLost,
+        // raised by the certification of conflicting writes
+        antidote_pb::Error::Server(antidote_pb::ABORTED) => AbortReason::Serialization,
+        antidote_pb::Error::Server(antidote_pb::TIMEOUT) => AbortReason::Timeout,
+        _ => AbortReason::Other,
+    }
+}
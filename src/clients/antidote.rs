@@ -0,0 +1,237 @@
+use crate::db::clock::Clock;
+use crate::db::cluster::{Cluster, ClusterNode, Node};
+use crate::db::error::DbcopError;
+use crate::db::history::{AbortReason, HistParams, Transaction};
+use crate::db::retry::{Deadline, RetryPolicy};
+
+use super::abort;
+use super::antidote_pb::{Connection, Register};
+
+#[derive(Debug, Clone)]
+pub struct AntidoteNode {
+    node: Node,
+    /// commit timestamp of the initialization, the first transaction of a session starts after it
+    timestamp: Option<Vec<u8>>,
+    /// start every transaction after the commit of the previous one in the session
+    propagate: bool,
+}
+
+impl AntidoteNode {
+    fn new(node: Node, propagate: bool) -> Self {
+        AntidoteNode {
+            node,
+            timestamp: None,
+            propagate,
+        }
+    }
+
+    fn connect(&self) -> Result<Connection, DbcopError> {
+        Connection::connect(&self.node.addr).map_err(|e| DbcopError::connection(self.node.addr, e))
+    }
+}
+
+impl ClusterNode for AntidoteNode {
+    fn exec_session(&self, hist: &mut Vec<Transaction>, retry: &RetryPolicy, clock: &Clock) -> Result<(), DbcopError> {
+        // dropped after the connection was lost, reconnected by the next attempt
+        let mut conn = Some(self.connect()?);
+        // causal dependency of the next transaction
+        let mut timestamp = self.timestamp.clone();
+
+        let mut executed = Vec::with_capacity(hist.len());
+        for mut transaction in hist.drain(..) {
+            let aborted = retry.run(&mut transaction, clock, |transaction, deadline| {
+                let antidote = match conn.as_mut() {
+                    Some(antidote) => antidote,
+                    None => match self.connect() {
+                        Ok(antidote) => conn.insert(antidote),
+                        Err(_) => return Ok(Some(AbortReason::ConnectionLost)),
+                    },
+                };
+                // without propagation every transaction may run on a stale snapshot
+                let start = if self.propagate { timestamp.as_deref() } else { None };
+                match exec_transaction(antidote, start, transaction, deadline)? {
+                    Ok(commit_time) => {
+                        timestamp = Some(commit_time);
+                        Ok(None)
+                    }
+                    Err(AbortReason::ConnectionLost) => {
+                        conn = None;
+                        Ok(Some(AbortReason::ConnectionLost))
+                    }
+                    Err(reason) => Ok(Some(reason)),
+                }
+            })?;
+            executed.extend(aborted);
+            executed.push(transaction);
+        }
+        *hist = executed;
+
+        Ok(())
+    }
+}
+
+fn register(variable: usize) -> Register {
+    Register {
+        key: variable.to_string().into_bytes(),
+        bucket: b"dbcop".to_vec(),
+    }
+}
+
+fn encode(value: usize) -> Vec<u8> {
+    (value as u64).to_be_bytes().to_vec()
+}
+
+/// Registers are written as big endian `u64`, a register never written reads as empty.
+fn decode(bytes: &[u8]) -> Result<usize, DbcopError> {
+    match <[u8; 8]>::try_from(bytes) {
+        Ok(bytes) => Ok(u64::from_be_bytes(bytes) as usize),
+        Err(_) if bytes.is_empty() => Ok(0),
+        Err(_) => Err(DbcopError::protocol(format!("register of {} bytes", bytes.len()))),
+    }
+}
+
+/// Executes `transaction` once after `timestamp`, returns its commit timestamp or why it aborted.
+fn exec_transaction(
+    conn: &mut Connection,
+    timestamp: Option<&[u8]>,
+    transaction: &mut Transaction,
+    deadline: Deadline,
+) -> Result<Result<Vec<u8>, AbortReason>, DbcopError> {
+    let db_transaction = match conn.start_transaction(timestamp) {
+        Ok(db_transaction) => db_transaction,
+        Err(e) => return Ok(Err(abort::antidote(&e))),
+    };
+
+    match exec_events(conn, &db_transaction, transaction, deadline) {
+        Ok(None) => Ok(conn.commit_transaction(&db_transaction).map_err(|e| abort::antidote(&e))),
+        // the server keeps the transaction open until it is aborted, whatever failed in it
+        Ok(Some(reason)) => {
+            if reason != AbortReason::ConnectionLost {
+                let _ = conn.abort_transaction(&db_transaction);
+            }
+            Ok(Err(reason))
+        }
+        Err(e) => {
+            let _ = conn.abort_transaction(&db_transaction);
+            Err(e)
+        }
+    }
+}
+
+/// Executes the events of `transaction` in `db_transaction`, returns why it aborted or `None` if
+/// it can commit.
+fn exec_events(
+    conn: &mut Connection,
+    db_transaction: &[u8],
+    transaction: &mut Transaction,
+    deadline: Deadline,
+) -> Result<Option<AbortReason>, DbcopError> {
+    for event in transaction.events.iter_mut() {
+        if deadline.expired() {
+            return Ok(Some(AbortReason::Timeout));
+        }
+        if event.write {
+            let update = (register(event.variable), encode(event.value));
+            if let Err(e) = conn.update_registers(db_transaction, &[update]) {
+                return Ok(Some(abort::antidote(&e)));
+            }
+        } else {
+            match conn.read_registers(db_transaction, &[register(event.variable)]) {
+                Ok(values) => event.value = decode(&values[0])?,
+                Err(e) => return Ok(Some(abort::antidote(&e))),
+            }
+        }
+        event.success = true;
+    }
+
+    if deadline.expired() {
+        return Ok(Some(AbortReason::Timeout));
+    }
+    Ok(None)
+}
+
+#[derive(Debug)]
+pub struct AntidoteCluster(Vec<AntidoteNode>);
+
+impl AntidoteCluster {
+    /// Sessions start every transaction after the commit of their previous one, unless
+    /// `propagate` is unset, which breaks causal consistency on purpose.
+    pub fn new(ips: &Vec<&str>, propagate: bool) -> Self {
+        let nodes = AntidoteCluster::node_vec(ips)
+            .into_iter()
+            .map(|node| AntidoteNode::new(node, propagate))
+            .collect();
+        AntidoteCluster(nodes)
+    }
+
+    fn create_variables(&mut self, n_variable: usize) -> Result<(), DbcopError> {
+        let node = self.0.first().ok_or_else(|| DbcopError::setup("no node given"))?;
+        let mut conn = node.connect()?;
+
+        let db_transaction = conn
+            .start_transaction(None)
+            .map_err(|e| DbcopError::setup(format!("error to start init: {}", e)))?;
+
+        let updates: Vec<_> = (0..n_variable).map(|variable| (register(variable), encode(0))).collect();
+
+        conn.update_registers(&db_transaction, &updates)
+            .map_err(|e| DbcopError::setup(format!("error to init zero values: {}", e)))?;
+
+        let commit_time = conn
+            .commit_transaction(&db_transaction)
+            .map_err(|e| DbcopError::setup(format!("commit error while init: {}", e)))?;
+
+        // wait until every node observed the initial values
+        for node in self.0.iter_mut() {
+            node.timestamp = Some(commit_time.clone());
+
+            let mut conn = node.connect()?;
+            let db_transaction = conn
+                .start_transaction(node.timestamp.as_deref())
+                .map_err(|e| DbcopError::setup(format!("error to start init: {}", e)))?;
+            let registers: Vec<_> = (0..n_variable).map(register).collect();
+
+            let values = conn
+                .read_registers(&db_transaction, &registers)
+                .map_err(|e| DbcopError::setup(format!("error to read zero values: {}", e)))?;
+            for value in values.iter() {
+                if decode(value)? != 0 {
+                    return Err(DbcopError::setup("variables are not zero after init"));
+                }
+            }
+
+            conn.commit_transaction(&db_transaction)
+                .map_err(|e| DbcopError::setup(format!("commit error while init: {}", e)))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Cluster<AntidoteNode> for AntidoteCluster {
+    fn n_node(&self) -> usize {
+        self.0.len()
+    }
+    fn setup(&self) -> Result<(), DbcopError> {
+        // registers need no schema
+        Ok(())
+    }
+    fn get_node(&self, id: usize) -> Node {
+        self.0[id].node.clone()
+    }
+    fn get_cluster_node(&self, id: usize) -> AntidoteNode {
+        self.0[id].clone()
+    }
+    fn setup_test(&mut self, p: &HistParams) -> Result<(), DbcopError> {
+        self.create_variables(p.get_n_variable())
+    }
+    fn cleanup(&self) -> Result<(), DbcopError> {
+        Ok(())
+    }
+    fn info(&self) -> String {
+        match self.0.first() {
+            Some(node) if !node.propagate => "AntidoteDB (no timestamp propagation)".to_string(),
+            _ => "AntidoteDB".to_string(),
+        }
+    }
+}
@@ -0,0 +1,316 @@
+//! Client of the protocol buffer interface of AntidoteDB, limited to what the driver needs:
+//! interactive transactions over last-writer-wins registers.
+//!
+//! Every message is framed by its length as a big endian `u32` followed by a byte naming its type,
+//! see `antidote_pb_codec` of AntidoteDB for the codes and `antidote.proto` for the messages.
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+
+const ERROR_RESP: u8 = 0;
+const OPERATION_RESP: u8 = 111;
+const READ_OBJECTS: u8 = 116;
+const UPDATE_OBJECTS: u8 = 118;
+const START_TRANSACTION: u8 = 119;
+const ABORT_TRANSACTION: u8 = 120;
+const COMMIT_TRANSACTION: u8 = 121;
+const START_TRANSACTION_RESP: u8 = 124;
+const READ_OBJECTS_RESP: u8 = 126;
+const COMMIT_RESP: u8 = 127;
+
+/// `CRDT_type` of a last-writer-wins register
+const LWWREG: u64 = 5;
+
+/// Error code the server reports a transaction it aborted with, certification conflicts included.
+pub const ABORTED: u32 = 3;
+/// Error code the server reports an expired operation with.
+pub const TIMEOUT: u32 = 1;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// the server refused the request, with one of its error codes
+    Server(u32),
+    /// the server answered something that is not a valid response to the request
+    Protocol(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Server(code) => write!(f, "request failed with error code {}", code),
+            Error::Protocol(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// Register `key` of `bucket`.
+#[derive(Debug, Clone)]
+pub struct Register {
+    pub key: Vec<u8>,
+    pub bucket: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct Connection(TcpStream);
+
+impl Connection {
+    pub fn connect(addr: &SocketAddr) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        Ok(Connection(stream))
+    }
+
+    /// Starts a transaction observing every transaction committed at or before `timestamp`,
+    /// returns its descriptor.
+    pub fn start_transaction(&mut self, timestamp: Option<&[u8]>) -> Result<Vec<u8>, Error> {
+        let mut msg = Vec::new();
+        if let Some(timestamp) = timestamp {
+            bytes_field(&mut msg, 1, timestamp);
+        }
+        // default transaction properties
+        bytes_field(&mut msg, 2, &[]);
+        let resp = self.request(START_TRANSACTION, &msg, START_TRANSACTION_RESP)?;
+        success(&resp, 3)?;
+        find_bytes(&resp, 2)
+            .map(|descriptor| descriptor.to_vec())
+            .ok_or_else(|| Error::Protocol("transaction started without a descriptor".to_string()))
+    }
+
+    /// Assigns every register its value in `transaction`.
+    pub fn update_registers(&mut self, transaction: &[u8], updates: &[(Register, Vec<u8>)]) -> Result<(), Error> {
+        let mut msg = Vec::new();
+        for (register, value) in updates {
+            let mut regop = Vec::new();
+            bytes_field(&mut regop, 1, value);
+            let mut operation = Vec::new();
+            bytes_field(&mut operation, 3, &regop);
+            let mut update = Vec::new();
+            bytes_field(&mut update, 1, &bound_object(register));
+            bytes_field(&mut update, 2, &operation);
+            bytes_field(&mut msg, 1, &update);
+        }
+        bytes_field(&mut msg, 2, transaction);
+        let resp = self.request(UPDATE_OBJECTS, &msg, OPERATION_RESP)?;
+        success(&resp, 2)
+    }
+
+    /// Values of `registers` in `transaction`, in the same order; a register never written is empty.
+    pub fn read_registers(&mut self, transaction: &[u8], registers: &[Register]) -> Result<Vec<Vec<u8>>, Error> {
+        let mut msg = Vec::new();
+        for register in registers {
+            bytes_field(&mut msg, 1, &bound_object(register));
+        }
+        bytes_field(&mut msg, 2, transaction);
+        let resp = self.request(READ_OBJECTS, &msg, READ_OBJECTS_RESP)?;
+        success(&resp, 3)?;
+
+        let mut values = Vec::with_capacity(registers.len());
+        for (field, value) in fields(&resp)? {
+            if let (2, Value::Bytes(object)) = (field, value) {
+                let reg = find_bytes(object, 3).ok_or_else(|| Error::Protocol("read a non-register".to_string()))?;
+                values.push(find_bytes(reg, 1).unwrap_or_default().to_vec());
+            }
+        }
+        if values.len() != registers.len() {
+            return Err(Error::Protocol(format!("read {} of {} registers", values.len(), registers.len())));
+        }
+        Ok(values)
+    }
+
+    /// Commits `transaction`, returns its commit timestamp.
+    pub fn commit_transaction(&mut self, transaction: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut msg = Vec::new();
+        bytes_field(&mut msg, 1, transaction);
+        let resp = self.request(COMMIT_TRANSACTION, &msg, COMMIT_RESP)?;
+        success(&resp, 3)?;
+        find_bytes(&resp, 2)
+            .map(|commit_time| commit_time.to_vec())
+            .ok_or_else(|| Error::Protocol("transaction committed without a timestamp".to_string()))
+    }
+
+    pub fn abort_transaction(&mut self, transaction: &[u8]) -> Result<(), Error> {
+        let mut msg = Vec::new();
+        bytes_field(&mut msg, 1, transaction);
+        let resp = self.request(ABORT_TRANSACTION, &msg, OPERATION_RESP)?;
+        success(&resp, 2)
+    }
+
+    /// Sends the message `msg` of type `code`, returns the response of type `resp_code`.
+    fn request(&mut self, code: u8, msg: &[u8], resp_code: u8) -> Result<Vec<u8>, Error> {
+        let mut frame = Vec::with_capacity(msg.len() + 5);
+        frame.extend_from_slice(&(msg.len() as u32 + 1).to_be_bytes());
+        frame.push(code);
+        frame.extend_from_slice(msg);
+        self.0.write_all(&frame)?;
+
+        let mut len = [0; 4];
+        self.0.read_exact(&mut len)?;
+        let len = u32::from_be_bytes(len) as usize;
+        if len == 0 {
+            return Err(Error::Protocol("empty response".to_string()));
+        }
+        let mut resp = vec![0; len];
+        self.0.read_exact(&mut resp)?;
+        match resp[0] {
+            code if code == resp_code => Ok(resp.split_off(1)),
+            // ApbErrorResp, the error code is its second field
+            ERROR_RESP => Err(Error::Server(find_varint(&resp[1..], 2).unwrap_or_default() as u32)),
+            code => Err(Error::Protocol(format!("response of type {} to a request of type {}", code, resp_code))),
+        }
+    }
+}
+
+/// `ApbBoundObject` of `register`.
+fn bound_object(register: &Register) -> Vec<u8> {
+    let mut msg = Vec::new();
+    bytes_field(&mut msg, 1, &register.key);
+    varint_field(&mut msg, 2, LWWREG);
+    bytes_field(&mut msg, 3, &register.bucket);
+    msg
+}
+
+/// Fails with the error code in field `errorcode` unless the field `success` of the response is set.
+fn success(resp: &[u8], errorcode: u32) -> Result<(), Error> {
+    match find_varint(resp, 1) {
+        Some(0) | None => Err(Error::Server(find_varint(resp, errorcode).unwrap_or_default() as u32)),
+        Some(_) => Ok(()),
+    }
+}
+
+fn varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn varint_field(buf: &mut Vec<u8>, field: u32, value: u64) {
+    varint(buf, (field as u64) << 3);
+    varint(buf, value);
+}
+
+fn bytes_field(buf: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+    varint(buf, (field as u64) << 3 | 2);
+    varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    /// fixed size fields, none of the responses read has one
+    Fixed,
+}
+
+fn read_varint(buf: &mut &[u8]) -> Result<u64, Error> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = buf
+            .split_first()
+            .ok_or_else(|| Error::Protocol("truncated varint".to_string()))?;
+        *buf = rest;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte < 0x80 {
+            return Ok(value);
+        }
+    }
+    Err(Error::Protocol("varint longer than 64 bits".to_string()))
+}
+
+fn take<'a>(buf: &mut &'a [u8], len: usize) -> Result<&'a [u8], Error> {
+    if buf.len() < len {
+        return Err(Error::Protocol("truncated field".to_string()));
+    }
+    let (taken, rest) = buf.split_at(len);
+    *buf = rest;
+    Ok(taken)
+}
+
+/// Fields of the message `msg` in order, with their numbers.
+fn fields(mut msg: &[u8]) -> Result<Vec<(u32, Value<'_>)>, Error> {
+    let mut fields = Vec::new();
+    while !msg.is_empty() {
+        let key = read_varint(&mut msg)?;
+        let value = match key & 7 {
+            0 => Value::Varint(read_varint(&mut msg)?),
+            1 => take(&mut msg, 8).map(|_| Value::Fixed)?,
+            2 => {
+                let len = read_varint(&mut msg)? as usize;
+                Value::Bytes(take(&mut msg, len)?)
+            }
+            5 => take(&mut msg, 4).map(|_| Value::Fixed)?,
+            wire_type => return Err(Error::Protocol(format!("field of wire type {}", wire_type))),
+        };
+        fields.push(((key >> 3) as u32, value));
+    }
+    Ok(fields)
+}
+
+fn find_varint(msg: &[u8], field: u32) -> Option<u64> {
+    fields(msg).ok()?.into_iter().find_map(|(number, value)| match value {
+        Value::Varint(value) if number == field => Some(value),
+        _ => None,
+    })
+}
+
+fn find_bytes(msg: &[u8], field: u32) -> Option<&[u8]> {
+    fields(msg).ok()?.into_iter().find_map(|(number, value)| match value {
+        Value::Bytes(bytes) if number == field => Some(bytes),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_a_bound_register() {
+        let register = Register {
+            key: b"7".to_vec(),
+            bucket: b"dbcop".to_vec(),
+        };
+        assert_eq!(
+            bound_object(&register),
+            [0x0a, 1, b'7', 0x10, 5, 0x1a, 5, b'd', b'b', b'c', b'o', b'p']
+        );
+    }
+
+    #[test]
+    fn decodes_what_it_encodes() {
+        let mut msg = Vec::new();
+        varint_field(&mut msg, 1, 1);
+        bytes_field(&mut msg, 2, b"commit time");
+        varint_field(&mut msg, 3, 300);
+        assert_eq!(
+            fields(&msg).unwrap(),
+            [
+                (1, Value::Varint(1)),
+                (2, Value::Bytes(b"commit time")),
+                (3, Value::Varint(300))
+            ]
+        );
+        assert!(success(&msg, 3).is_ok());
+        assert_eq!(find_bytes(&msg, 2), Some(&b"commit time"[..]));
+    }
+
+    #[test]
+    fn reports_the_error_code_of_a_failed_response() {
+        let mut msg = Vec::new();
+        varint_field(&mut msg, 1, 0);
+        varint_field(&mut msg, 3, ABORTED as u64);
+        assert!(matches!(success(&msg, 3), Err(Error::Server(ABORTED))));
+        assert!(matches!(fields(&msg[..msg.len() - 1]), Err(Error::Protocol(_))));
+    }
+}
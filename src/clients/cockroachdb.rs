@@ -0,0 +1,82 @@
+use crate::db::cluster::{Cluster, Init, Isolation, Node};
+use crate::db::error::DbcopError;
+use crate::db::history::HistParams;
+
+use super::pg_wire::PgWireNode;
+
+use postgres::{Client, NoTls};
+
+#[derive(Debug)]
+pub struct CockroachCluster(Vec<Node>, Isolation, Init);
+
+impl CockroachCluster {
+    pub fn new(ips: &Vec<&str>, isolation: Isolation, init: Init) -> Self {
+        CockroachCluster(CockroachCluster::node_vec(ips), isolation, init)
+    }
+
+    fn create_table(&self) -> Result<(), DbcopError> {
+        let mut conn = self.connect()?;
+        conn.execute("CREATE DATABASE IF NOT EXISTS dbcop", &[]).map_err(DbcopError::setup)?;
+        conn.execute("DROP TABLE IF EXISTS dbcop.variables", &[]).map_err(DbcopError::setup)?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS dbcop.variables (var INT8 NOT NULL PRIMARY KEY, val INT8 NOT NULL)", &[]
+        ).map_err(DbcopError::setup)?;
+        Ok(())
+    }
+
+    fn create_variables(&self, n_variable: usize) -> Result<(), DbcopError> {
+        if self.2 == Init::Lazy {
+            // variables are created by their first write
+            return Ok(());
+        }
+
+        let mut conn = self.connect()?;
+        let stmt = conn
+            .prepare("INSERT INTO dbcop.variables (var, val) values ($1, 0)")
+            .map_err(DbcopError::setup)?;
+        for variable in 0..n_variable {
+            conn.execute(&stmt, &[&(variable as i64)]).map_err(DbcopError::setup)?;
+        }
+        Ok(())
+    }
+
+    fn drop_database(&self) -> Result<(), DbcopError> {
+        let mut conn = self.connect()?;
+        conn.execute("DROP DATABASE dbcop CASCADE", &[]).map_err(DbcopError::setup)?;
+        Ok(())
+    }
+
+    fn connect(&self) -> Result<Client, DbcopError> {
+        let ip = self.get_postgresql_addr(0).ok_or_else(|| DbcopError::setup("no node given"))?;
+        Client::connect(ip.as_str(), NoTls).map_err(|e| DbcopError::connection(self.0[0].addr, e))
+    }
+
+    fn get_postgresql_addr(&self, i: usize) -> Option<String> {
+        self.0.get(i).map(|node| format!("postgresql://{}@{}", "root", node.addr))
+    }
+}
+
+impl Cluster<PgWireNode> for CockroachCluster {
+    fn n_node(&self) -> usize {
+        self.0.len()
+    }
+    fn setup(&self) -> Result<(), DbcopError> {
+        self.create_table()
+    }
+    fn get_node(&self, id: usize) -> Node {
+        self.0[id].clone()
+    }
+    fn get_cluster_node(&self, id: usize) -> PgWireNode {
+        let node = self.get_node(id);
+        PgWireNode::new(format!("postgresql://{}@{}", "root", node.addr), &node, self.1, self.2, None)
+    }
+    fn setup_test(&mut self, p: &HistParams) -> Result<(), DbcopError> {
+        self.create_variables(p.get_n_variable())
+    }
+    fn cleanup(&self) -> Result<(), DbcopError> {
+        self.drop_database()
+    }
+    fn info(&self) -> String {
+        format!("CockroachDB ({}, {})", self.1, self.2)
+    }
+}
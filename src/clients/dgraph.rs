@@ -1,12 +1,13 @@
-use std::fs;
 use std::net::SocketAddr;
-use std::path::Path;
 use std::collections::HashMap;
 
+use crate::db::clock::Clock;
 use crate::db::cluster::{Cluster, ClusterNode, Node};
-use crate::db::history::{HistParams, Transaction};
+use crate::db::error::DbcopError;
+use crate::db::history::{AbortReason, HistParams, Transaction};
+use crate::db::retry::{Deadline, RetryPolicy};
 
-use clap::{App, Arg};
+use super::abort;
 
 use dgraph_tonic::sync::{Client, Mutate, Query};
 use dgraph_tonic::{Operation, Mutation, Response};
@@ -29,9 +30,11 @@ struct All {
     all: Vec<KeyValuePair>,
 }
 
-impl From<Response> for All {
-    fn from(r: Response) -> Self {
-        serde_json::from_slice(&r.json).unwrap()
+impl TryFrom<Response> for All {
+    type Error = DbcopError;
+
+    fn try_from(r: Response) -> Result<Self, Self::Error> {
+        serde_json::from_slice(&r.json).map_err(DbcopError::protocol)
     }
 }
 
@@ -45,37 +48,62 @@ impl From<Node> for DGraphNode {
 }
 
 impl ClusterNode for DGraphNode {
-    fn exec_session(&self, hist: &mut Vec<Transaction>) {
-        let client = Client::new(format!("http://{}", self.addr)).unwrap();
-
-        for transaction in hist.iter_mut() {
-            while !transaction.success {
-                let mut txn = client.new_mutated_txn();
-
-                for event in transaction.events.iter_mut() {
-                    if event.write {
-                        let mut mu = Mutation::new();
-                        mu.set_set_json(&KeyValuePair { uid: (event.variable + 1).to_string(), val: event.value }).expect("set_set_json");
-                        txn.mutate(mu).unwrap();
-                        event.success = true;
-                    } else {
-                        let result = txn.query(format!("query {{ all(func: uid({})) {{ uid, val }} }}", event.variable + 1));
-                        let all: All = result.unwrap().try_into().unwrap();
-                        event.value = all.all[0].val as usize;
-                        event.success = true;
-                    }
-                }
-
-                transaction.success = match txn.commit() {
-                    Ok(_) => true,
-                    Err(_e) => {
-                        // println!("{:?} -- COMMIT ERROR {}", transaction, _e.root_cause());
-                        false
-                    },
-                };
+    fn exec_session(&self, hist: &mut Vec<Transaction>, retry: &RetryPolicy, clock: &Clock) -> Result<(), DbcopError> {
+        let client = Client::new(format!("http://{}", self.addr)).map_err(|e| DbcopError::connection(self.addr, e))?;
+
+        let mut executed = Vec::with_capacity(hist.len());
+        for mut transaction in hist.drain(..) {
+            let aborted = retry.run(&mut transaction, clock, |transaction, deadline| exec_transaction(&client, transaction, deadline))?;
+            executed.extend(aborted);
+            executed.push(transaction);
+        }
+        *hist = executed;
+
+        Ok(())
+    }
+}
+
+/// Executes `transaction` once, returns why it aborted or `None` if it committed.
+fn exec_transaction(
+    client: &Client,
+    transaction: &mut Transaction,
+    deadline: Deadline,
+) -> Result<Option<AbortReason>, DbcopError> {
+    let mut txn = client.new_mutated_txn();
+
+    for event in transaction.events.iter_mut() {
+        if deadline.expired() {
+            return Ok(Some(AbortReason::Timeout));
+        }
+        if event.write {
+            let mut mu = Mutation::new();
+            mu.set_set_json(&KeyValuePair { uid: (event.variable + 1).to_string(), val: event.value })
+                .map_err(DbcopError::protocol)?;
+            if let Err(e) = txn.mutate(mu) {
+                return Ok(Some(abort::dgraph(&e)));
             }
+            event.success = true;
+        } else {
+            let result = match txn.query(format!("query {{ all(func: uid({})) {{ uid, val }} }}", event.variable + 1)) {
+                Ok(result) => result,
+                Err(e) => return Ok(Some(abort::dgraph(&e))),
+            };
+            let all: All = result.try_into()?;
+            let kv = all.all.first().ok_or_else(|| {
+                DbcopError::protocol(format!("variable {} does not exist", event.variable))
+            })?;
+            event.value = kv.val as usize;
+            event.success = true;
         }
     }
+
+    if deadline.expired() {
+        return Ok(Some(AbortReason::Timeout));
+    }
+    match txn.commit() {
+        Ok(_) => Ok(None),
+        Err(e) => Ok(Some(abort::dgraph(&e))),
+    }
 }
 
 #[derive(Debug)]
@@ -86,14 +114,13 @@ impl DGraphCluster {
         DGraphCluster(DGraphCluster::node_vec(ips))
     }
 
-    fn create_table(&self) -> bool {
-        let http_addr = format!("http://{}", self.get_dgraph_addr(0).unwrap());
-        let client = Client::new(http_addr).unwrap();
+    fn create_table(&self) -> Result<(), DbcopError> {
+        let client = self.client()?;
 
         client.alter(Operation {
             drop_all: true,
             ..Default::default()
-        }).expect("alter");
+        }).map_err(DbcopError::setup)?;
 
         client.alter(Operation {
             schema: r#"
@@ -104,27 +131,34 @@ type KV {
 }
 "#.to_string(),
             ..Default::default()
-        }).expect("alter");
+        }).map_err(DbcopError::setup)?;
 
-        true
+        Ok(())
     }
 
-    fn create_variables(&self, n_variable: usize) {
-        let client = Client::new(format!("http://{}", self.get_dgraph_addr(0).unwrap())).unwrap();
+    fn create_variables(&self, n_variable: usize) -> Result<(), DbcopError> {
+        let client = self.client()?;
         let mut txn = client.new_mutated_txn();
         let data = All { all: (1..n_variable+1).map(|uid| KeyValuePair { uid: uid.to_string(), val: 0 }).collect() };
         let mut mu = Mutation::new();
-        mu.set_set_json(&data).unwrap();
-        txn.mutate(mu).unwrap();
-        txn.commit().unwrap();
+        mu.set_set_json(&data).map_err(DbcopError::setup)?;
+        txn.mutate(mu).map_err(DbcopError::setup)?;
+        txn.commit().map_err(DbcopError::setup)?;
+        Ok(())
     }
 
-    fn drop_database(&self) {
-        let client = Client::new(format!("http://{}", self.get_dgraph_addr(0).unwrap())).unwrap();
+    fn drop_database(&self) -> Result<(), DbcopError> {
+        let client = self.client()?;
         client.alter(Operation {
             drop_all: true,
             ..Default::default()
-        }).expect("alter");
+        }).map_err(DbcopError::setup)?;
+        Ok(())
+    }
+
+    fn client(&self) -> Result<Client, DbcopError> {
+        let addr = self.get_dgraph_addr(0).ok_or_else(|| DbcopError::setup("no node given"))?;
+        Client::new(format!("http://{}", addr)).map_err(|e| DbcopError::connection(addr, e))
     }
 
     fn get_dgraph_addr(&self, i: usize) -> Option<SocketAddr> {
@@ -136,7 +170,7 @@ impl Cluster<DGraphNode> for DGraphCluster {
     fn n_node(&self) -> usize {
         self.0.len()
     }
-    fn setup(&self) -> bool {
+    fn setup(&self) -> Result<(), DbcopError> {
         self.create_table()
     }
     fn get_node(&self, id: usize) -> Node {
@@ -145,51 +179,13 @@ impl Cluster<DGraphNode> for DGraphCluster {
     fn get_cluster_node(&self, id: usize) -> DGraphNode {
         From::from(self.get_node(id))
     }
-    fn setup_test(&mut self, p: &HistParams) {
-        self.create_variables(p.get_n_variable());
+    fn setup_test(&mut self, p: &HistParams) -> Result<(), DbcopError> {
+        self.create_variables(p.get_n_variable())
     }
-    fn cleanup(&self) {
-        self.drop_database();
+    fn cleanup(&self) -> Result<(), DbcopError> {
+        self.drop_database()
     }
     fn info(&self) -> String {
         "Dgraph".to_string()
     }
 }
-
-// fn main() {
-//     let matches = App::new("DGraph")
-//         .version("1.0")
-//         .author("Ranadeep")
-//         .about("executes histories on DGraph")
-//         .arg(
-//             Arg::with_name("hist_dir")
-//                 .long("dir")
-//                 .short("d")
-//                 .takes_value(true)
-//                 .required(true),
-//         )
-//         .arg(
-//             Arg::with_name("hist_out")
-//                 .long("out")
-//                 .short("o")
-//                 .takes_value(true)
-//                 .required(true),
-//         )
-//         .arg(
-//             Arg::with_name("ip:port")
-//                 .help("DB addr")
-//                 .required(true),
-//         )
-//         .get_matches();
-
-//     let hist_dir = Path::new(matches.value_of("hist_dir").unwrap());
-//     let hist_out = Path::new(matches.value_of("hist_out").unwrap());
-
-//     fs::create_dir_all(hist_out).expect("couldn't create directory");
-
-//     let ips: Vec<_> = matches.values_of("ip:port").unwrap().collect();
-
-//     let mut cluster = DGraphCluster::new(&ips);
-
-//     cluster.execute_all(hist_dir, hist_out, 100);
-// }
@@ -1,6 +1,9 @@
 use std::marker::PhantomData;
 
+use crate::db::clock::Clock;
 use crate::db::cluster::{Cluster, ClusterNode};
+use crate::db::error::DbcopError;
+use crate::db::retry::RetryPolicy;
 
 pub struct DynCluster<N, C>
 where
@@ -16,8 +19,8 @@ pub struct DynNode {
 }
 
 impl ClusterNode for DynNode {
-    fn exec_session(&self, hist: &mut crate::db::history::Session) {
-        self.node.exec_session(hist)
+    fn exec_session(&self, hist: &mut crate::db::history::Session, retry: &RetryPolicy, clock: &Clock) -> Result<(), DbcopError> {
+        self.node.exec_session(hist, retry, clock)
     }
 }
 
@@ -30,11 +33,11 @@ where
         self.cluster.n_node()
     }
 
-    fn setup(&self) -> bool {
+    fn setup(&self) -> Result<(), DbcopError> {
         self.cluster.setup()
     }
 
-    fn setup_test(&mut self, p: &crate::db::history::HistParams) {
+    fn setup_test(&mut self, p: &crate::db::history::HistParams) -> Result<(), DbcopError> {
         self.cluster.setup_test(p)
     }
 
@@ -48,7 +51,7 @@ where
         }
     }
 
-    fn cleanup(&self) {
+    fn cleanup(&self) -> Result<(), DbcopError> {
         self.cluster.cleanup()
     }
 
@@ -65,7 +68,7 @@ where
     pub fn new(cluster: C) -> Self {
         DynCluster {
             cluster,
-            node_type: PhantomData::default(),
+            node_type: PhantomData,
         }
     }
 }
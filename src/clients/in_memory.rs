@@ -0,0 +1,467 @@
+//! A multi-version key-value store inside the dbcop process, to run histories without a database.
+//! Every committed write is a version stamped with a commit timestamp; transactions read the
+//! latest version in a snapshot, which `Mode` chooses: a prefix of the commits or, in
+//! `Mode::Causal`, a causally closed set of transactions.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+
+use crate::db::clock::Clock;
+use crate::db::cluster::{Cluster, ClusterNode, Node};
+use crate::db::error::DbcopError;
+use crate::db::history::{AbortReason, Event, HistParams, Transaction};
+use crate::db::retry::{Deadline, RetryPolicy};
+
+use clap::ValueEnum;
+use rand::Rng;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Mode {
+    /// transactions run one at a time
+    #[default]
+    #[clap(name = "ser")]
+    Serializable,
+    /// reads from the latest snapshot at the start, the later of two concurrent writers of a
+    /// variable aborts
+    #[clap(name = "si")]
+    SnapshotIsolation,
+    /// every read returns the latest committed version
+    #[clap(name = "rc")]
+    ReadCommitted,
+    /// reads from a random causally closed set of transactions including everything the session
+    /// observed, so sessions may observe concurrent transactions in different orders
+    Causal,
+    /// snapshot isolation without aborts, concurrent writers of a variable all commit
+    LostUpdate,
+    /// reads from any snapshot, even one missing the earlier transactions of the session
+    StaleRead,
+    /// reads from a possibly stale snapshot including everything the session observed, while the
+    /// writes of a transaction become visible one at a time, in the order it made them
+    FracturedRead,
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let repr = match self {
+            Mode::Serializable => "ser",
+            Mode::SnapshotIsolation => "si",
+            Mode::ReadCommitted => "rc",
+            Mode::Causal => "causal",
+            Mode::LostUpdate => "lost-update",
+            Mode::StaleRead => "stale-read",
+            Mode::FracturedRead => "fractured-read",
+        };
+        write!(f, "{}", repr)
+    }
+}
+
+/// Causal past as a vector clock: the number of writing transactions of every session it
+/// contains, a missing session has none.
+type Past = Vec<usize>;
+
+fn join(past: &mut Past, other: &[usize]) {
+    if past.len() < other.len() {
+        past.resize(other.len(), 0);
+    }
+    for (count, &other) in past.iter_mut().zip(other) {
+        *count = (*count).max(other);
+    }
+}
+
+#[derive(Debug)]
+struct Version {
+    value: usize,
+    commit: u64,
+    /// session of the writing transaction, and its number among the writing transactions of the
+    /// session, counting from 1
+    writer: (usize, usize),
+}
+
+#[derive(Debug, Clone)]
+enum Snapshot {
+    /// every version committed up to the timestamp
+    At(u64),
+    /// the versions written by the transactions of the causal past
+    Causal(Past),
+}
+
+impl Snapshot {
+    fn includes(&self, version: &Version) -> bool {
+        match self {
+            Snapshot::At(timestamp) => version.commit <= *timestamp,
+            Snapshot::Causal(past) => {
+                let (session, number) = version.writer;
+                number <= past.get(session).copied().unwrap_or(0)
+            }
+        }
+    }
+}
+
+/// What a session observed so far.
+#[derive(Debug, Default)]
+struct SessionState {
+    id: usize,
+    /// latest commit timestamp the session read from or wrote at
+    seen: u64,
+    past: Past,
+}
+
+/// A transaction attempt in progress, executed one event at a time.
+#[derive(Debug)]
+struct Attempt {
+    snapshot: Snapshot,
+    /// written variables with their last value, in the order of their first write
+    writes: Vec<(usize, usize)>,
+}
+
+#[derive(Debug)]
+struct Store {
+    /// committed versions of every variable, oldest first; no version before the first write
+    /// reads as `0`
+    versions: HashMap<usize, Vec<Version>>,
+    /// timestamp of the latest commit
+    now: u64,
+    /// causal past of every writing transaction of every session, itself included
+    pasts: Vec<Vec<Past>>,
+    rng: ChaCha8Rng,
+}
+
+impl Store {
+    fn new(seed: u64) -> Self {
+        Store {
+            versions: HashMap::new(),
+            now: 0,
+            pasts: Vec::new(),
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    fn open_session(&mut self) -> SessionState {
+        self.pasts.push(Vec::new());
+        SessionState {
+            id: self.pasts.len() - 1,
+            ..SessionState::default()
+        }
+    }
+
+    fn read(&self, variable: usize, snapshot: &Snapshot) -> usize {
+        self.versions
+            .get(&variable)
+            .and_then(|versions| versions.iter().rev().find(|v| snapshot.includes(v)))
+            .map_or(0, |v| v.value)
+    }
+
+    fn written_since(&self, variable: usize, snapshot: u64) -> bool {
+        self.versions
+            .get(&variable)
+            .and_then(|versions| versions.last())
+            .is_some_and(|v| v.commit > snapshot)
+    }
+
+    /// Snapshot of the next transaction of `session`.
+    fn snapshot(&mut self, mode: Mode, session: &SessionState) -> Snapshot {
+        match mode {
+            Mode::Causal => {
+                // joins the pasts of random transactions, which keeps it causally closed
+                let mut past = session.past.clone();
+                for _ in 0..self.pasts.len() {
+                    let other = self.rng.gen_range(0..self.pasts.len());
+                    let number = self.rng.gen_range(0..=self.pasts[other].len());
+                    if number > 0 {
+                        join(&mut past, &self.pasts[other][number - 1]);
+                    }
+                }
+                Snapshot::Causal(past)
+            }
+            Mode::FracturedRead => Snapshot::At(self.rng.gen_range(session.seen.min(self.now)..=self.now)),
+            Mode::StaleRead => Snapshot::At(self.rng.gen_range(0..=self.now)),
+            _ => Snapshot::At(self.now),
+        }
+    }
+
+    fn begin(&mut self, mode: Mode, session: &SessionState) -> Attempt {
+        Attempt {
+            snapshot: self.snapshot(mode, session),
+            writes: Vec::new(),
+        }
+    }
+
+    fn exec_event(&self, mode: Mode, attempt: &mut Attempt, event: &mut Event) {
+        let written = attempt.writes.iter_mut().find(|(variable, _)| *variable == event.variable);
+        if event.write {
+            match written {
+                Some((_, value)) => *value = event.value,
+                None => attempt.writes.push((event.variable, event.value)),
+            }
+        } else {
+            event.value = match written {
+                Some((_, value)) => *value,
+                None if mode == Mode::ReadCommitted => self.read(event.variable, &Snapshot::At(self.now)),
+                None => self.read(event.variable, &attempt.snapshot),
+            };
+        }
+        event.success = true;
+    }
+
+    /// Commits `attempt` of `session`, returns why it aborted or `None` if it committed.
+    fn finish(&mut self, mode: Mode, attempt: Attempt, session: &mut SessionState) -> Option<AbortReason> {
+        match attempt.snapshot {
+            Snapshot::At(snapshot) => {
+                if mode == Mode::SnapshotIsolation && attempt.writes.iter().any(|&(var, _)| self.written_since(var, snapshot)) {
+                    return Some(AbortReason::Serialization);
+                }
+                session.seen = session.seen.max(snapshot);
+            }
+            Snapshot::Causal(past) => join(&mut session.past, &past),
+        }
+        if !attempt.writes.is_empty() {
+            self.commit(&attempt.writes, mode == Mode::FracturedRead, session);
+        }
+        None
+    }
+
+    /// Installs `writes` of `session` at one commit timestamp, or at one each if `fractured`.
+    fn commit(&mut self, writes: &[(usize, usize)], fractured: bool, session: &mut SessionState) {
+        let number = self.pasts[session.id].len() + 1;
+        if session.past.len() <= session.id {
+            session.past.resize(session.id + 1, 0);
+        }
+        session.past[session.id] = number;
+        self.pasts[session.id].push(session.past.clone());
+
+        self.now += 1;
+        for (i, &(variable, value)) in writes.iter().enumerate() {
+            if fractured && i > 0 {
+                self.now += 1;
+            }
+            self.versions.entry(variable).or_default().push(Version {
+                value,
+                commit: self.now,
+                writer: (session.id, number),
+            });
+        }
+        session.seen = self.now;
+    }
+}
+
+#[derive(Debug)]
+struct Shared {
+    store: Mutex<Store>,
+    /// held for a whole transaction in `Mode::Serializable`
+    serial: Mutex<()>,
+}
+
+impl Shared {
+    fn store(&self) -> MutexGuard<'_, Store> {
+        self.store.lock().expect("in-memory store poisoned")
+    }
+}
+
+#[derive(Debug)]
+pub struct InMemoryNode {
+    shared: Arc<Shared>,
+    mode: Mode,
+}
+
+impl ClusterNode for InMemoryNode {
+    fn exec_session(&self, hist: &mut Vec<Transaction>, retry: &RetryPolicy, clock: &Clock) -> Result<(), DbcopError> {
+        let mut session = self.shared.store().open_session();
+
+        let mut executed = Vec::with_capacity(hist.len());
+        for mut transaction in hist.drain(..) {
+            let aborted = retry.run(&mut transaction, clock, |transaction, deadline| {
+                Ok(exec_transaction(&self.shared, self.mode, &mut session, transaction, deadline))
+            })?;
+            executed.extend(aborted);
+            executed.push(transaction);
+        }
+        *hist = executed;
+
+        Ok(())
+    }
+}
+
+/// Executes `transaction` once, returns why it aborted or `None` if it committed.
+fn exec_transaction(
+    shared: &Shared,
+    mode: Mode,
+    session: &mut SessionState,
+    transaction: &mut Transaction,
+    deadline: Deadline,
+) -> Option<AbortReason> {
+    let _serial = match mode {
+        Mode::Serializable => Some(shared.serial.lock().expect("in-memory store poisoned")),
+        _ => None,
+    };
+
+    let mut attempt = shared.store().begin(mode, session);
+    for event in transaction.events.iter_mut() {
+        if deadline.expired() {
+            return Some(AbortReason::Timeout);
+        }
+        shared.store().exec_event(mode, &mut attempt, event);
+        // let other sessions interleave between events
+        thread::yield_now();
+    }
+
+    if deadline.expired() {
+        return Some(AbortReason::Timeout);
+    }
+    shared.store().finish(mode, attempt, session)
+}
+
+/// Runs every session against one store; it has a single node and is reset for every history,
+/// seeded by the given seed or else by the seed of the history.
+#[derive(Debug)]
+pub struct InMemoryCluster(Arc<Shared>, Mode, Option<u64>);
+
+impl InMemoryCluster {
+    pub fn new(mode: Mode, seed: Option<u64>) -> Self {
+        let shared = Shared {
+            store: Mutex::new(Store::new(0)),
+            serial: Mutex::new(()),
+        };
+        InMemoryCluster(Arc::new(shared), mode, seed)
+    }
+}
+
+impl Cluster<InMemoryNode> for InMemoryCluster {
+    fn n_node(&self) -> usize {
+        1
+    }
+    fn setup(&self) -> Result<(), DbcopError> {
+        Ok(())
+    }
+    fn get_node(&self, id: usize) -> Node {
+        Node {
+            addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+            id: id + 1,
+        }
+    }
+    fn get_cluster_node(&self, _id: usize) -> InMemoryNode {
+        InMemoryNode {
+            shared: self.0.clone(),
+            mode: self.1,
+        }
+    }
+    fn setup_test(&mut self, p: &HistParams) -> Result<(), DbcopError> {
+        *self.0.store() = Store::new(self.2.unwrap_or(p.get_seed()));
+        Ok(())
+    }
+    fn cleanup(&self) -> Result<(), DbcopError> {
+        Ok(())
+    }
+    fn info(&self) -> String {
+        format!("in-memory ({})", self.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consistency::Consistency;
+    use crate::db::distribution::KeyDistribution;
+    use crate::db::history::{generate_mult_histories, HistoryParams, Session};
+    use crate::verifier::Verifier;
+
+    use rand::seq::SliceRandom;
+
+    /// Executes `sessions` on a store in `mode`, stepping a random session at a time, drawn from
+    /// `seed`: it starts a transaction, executes its next event or commits it. Aborted attempts
+    /// are retried, and in `Mode::Serializable` a started transaction runs alone until it commits.
+    fn interleave(mode: Mode, sessions: &mut [Session], seed: u64) {
+        let mut store = Store::new(seed);
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let mut states: Vec<SessionState> = sessions.iter().map(|_| store.open_session()).collect();
+        // next transaction of every session, and its attempt in progress with its next event
+        let mut next = vec![0; sessions.len()];
+        let mut attempts: Vec<Option<(Attempt, usize)>> = sessions.iter().map(|_| None).collect();
+
+        loop {
+            let serial = mode == Mode::Serializable && attempts.iter().any(Option::is_some);
+            let ready: Vec<usize> = (0..sessions.len())
+                .filter(|&s| next[s] < sessions[s].len() && (!serial || attempts[s].is_some()))
+                .collect();
+            let s = match ready.choose(&mut rng) {
+                Some(&s) => s,
+                None => break,
+            };
+            let transaction = &mut sessions[s][next[s]];
+            match attempts[s].take() {
+                None => attempts[s] = Some((store.begin(mode, &states[s]), 0)),
+                Some((mut attempt, i)) if i < transaction.events.len() => {
+                    store.exec_event(mode, &mut attempt, &mut transaction.events[i]);
+                    attempts[s] = Some((attempt, i + 1));
+                }
+                Some((attempt, _)) => match store.finish(mode, attempt, &mut states[s]) {
+                    None => {
+                        transaction.success = true;
+                        next[s] += 1;
+                    }
+                    Some(_) => transaction.events.iter_mut().for_each(|event| event.success = false),
+                },
+            }
+        }
+    }
+
+    /// Executes generated histories of contended sessions in `mode`.
+    fn execute(mode: Mode) -> Vec<Vec<Session>> {
+        let histories = generate_mult_histories(HistoryParams {
+            n_hist: 20,
+            n_node: 4,
+            n_variable: 3,
+            n_transaction: 8,
+            n_event: 3,
+            read_probability: 0.5,
+            longtxn_proportion: 0.0,
+            longtxn_size: 1.0,
+            key_distribution: KeyDistribution::Uniform,
+            random_txn_size: false,
+            seed: 0,
+        });
+
+        histories
+            .iter()
+            .map(|hist| {
+                let mut sessions = hist.get_cloned_data();
+                interleave(mode, &mut sessions, hist.get_id() as u64);
+                sessions
+            })
+            .collect()
+    }
+
+    #[test]
+    fn modes_guarantee_their_level_and_no_stronger() {
+        // level every history satisfies, and a stronger one some history violates
+        let expected = [
+            (Mode::Serializable, Consistency::StrictSerializable, None),
+            (Mode::SnapshotIsolation, Consistency::SnapshotIsolation, Some(Consistency::Serializable)),
+            (Mode::ReadCommitted, Consistency::ReadCommitted, Some(Consistency::ReadAtomic)),
+            (Mode::Causal, Consistency::Causal, Some(Consistency::Prefix)),
+            (Mode::LostUpdate, Consistency::Prefix, Some(Consistency::ParallelSnapshotIsolation)),
+            (Mode::StaleRead, Consistency::ReadCommitted, Some(Consistency::ReadAtomic)),
+            (Mode::FracturedRead, Consistency::ReadCommitted, Some(Consistency::ReadAtomic)),
+        ];
+        assert_eq!(expected.len(), Mode::value_variants().len());
+
+        for (mode, guaranteed, rejected) in expected {
+            let executed = execute(mode);
+            for sessions in executed.iter() {
+                assert_eq!(Verifier::new(guaranteed).verify(sessions), None, "{} violates {:?}", mode, guaranteed);
+            }
+            if let Some(rejected) = rejected {
+                assert!(
+                    executed.iter().any(|sessions| Verifier::new(rejected).verify(sessions).is_some()),
+                    "{} never violates {:?}",
+                    mode,
+                    rejected
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,19 @@
+//! The isolation levels of the database drivers for an `Isolation`.
+
+use crate::db::cluster::Isolation;
+
+pub fn postgres(isolation: Isolation) -> postgres::IsolationLevel {
+    match isolation {
+        Isolation::ReadCommitted => postgres::IsolationLevel::ReadCommitted,
+        Isolation::RepeatableRead => postgres::IsolationLevel::RepeatableRead,
+        Isolation::Serializable => postgres::IsolationLevel::Serializable,
+    }
+}
+
+pub fn mysql(isolation: Isolation) -> mysql::IsolationLevel {
+    match isolation {
+        Isolation::ReadCommitted => mysql::IsolationLevel::ReadCommitted,
+        Isolation::RepeatableRead => mysql::IsolationLevel::RepeatableRead,
+        Isolation::Serializable => mysql::IsolationLevel::Serializable,
+    }
+}
@@ -1,21 +1,8 @@
 #include "memgraph.h"
 
-#include <cassert>
 #include <iostream>
 #include <string>
 
-static void begin_transaction(MgClient &client) {
-  // assert(client.BeginTransaction());
-  assert(client.Execute("BEGIN"));
-  client.DiscardAll();
-}
-
-static void commit_transaction(MgClient &client) {
-  // assert(client.CommitTransaction());
-  assert(client.Execute("COMMIT"));
-  client.DiscardAll();
-}
-
 void init() { mg::Client::Init(); }
 
 std::unique_ptr<MgClient> new_client(rust::Str ip, uint16_t port) {
@@ -25,60 +12,80 @@ std::unique_ptr<MgClient> new_client(rust::Str ip, uint16_t port) {
   return mg::Client::Connect(params);
 }
 
-void exec_transaction(MgClient &client, rust::Vec<Event> &transaction) {
-  bool success = false;
-  std::string write_stmt = "MATCH (n:KV {var: $var}) SET n.val = $val RETURN n.val;",
-              read_stmt = "MATCH (n:KV {var: $var}) RETURN n.val;";
+// the client throws once the connection is lost, a failed query only aborts the transaction
+static Outcome execute(MgClient &client, const std::string &stmt) {
+  try {
+    if (!client.Execute(stmt)) {
+      return Outcome::Aborted;
+    }
+    client.DiscardAll();
+  } catch (mg::ClientException &e) {
+    return Outcome::ConnectionLost;
+  }
+  return Outcome::Done;
+}
 
-  while (!success) {
-    begin_transaction(client);
-    success = true;
+Outcome begin_transaction(MgClient &client) { return execute(client, "BEGIN"); }
 
-    try {
-      for (auto &ev : transaction) {
-        mg::Map map{{"var", mg::Value(ev.key)}, {"val", mg::Value(ev.value)}};
+Outcome commit_transaction(MgClient &client) { return execute(client, "COMMIT"); }
 
-        if (ev.event_type == EventType::Read) {
-          if (!client.Execute(read_stmt, map.AsConstMap())) {
-            success = false;
-            break;
-          }
+Outcome rollback_transaction(MgClient &client) { return execute(client, "ROLLBACK"); }
 
-          auto result = client.FetchAll().value();
-          // std::cerr << "result: " << result.size() << result[0].size() << result[0][0].ValueInt() << '\n';
-          ev.value = result[0][0].ValueInt();
-        } else {
-          if (!client.Execute(write_stmt, map.AsConstMap())) {
-            success = false;
-            break;
-          }
+Outcome exec_event(MgClient &client, Event &ev) {
+  std::string write_stmt = "MATCH (n:KV {var: $var}) SET n.val = $val RETURN n.val;",
+              read_stmt = "MATCH (n:KV {var: $var}) RETURN n.val;";
+  mg::Map map{{"var", mg::Value(ev.key)}, {"val", mg::Value(ev.value)}};
 
-          auto result = client.FetchAll().value();
-          assert(ev.value == result[0][0].ValueInt());
-        }
-      }
-    } catch (mg::ClientException &e) {
-      success = false;
+  try {
+    if (!client.Execute(ev.event_type == EventType::Read ? read_stmt : write_stmt, map.AsConstMap())) {
+      return Outcome::Aborted;
     }
 
-    if (success) {
-      commit_transaction(client);
+    auto result = client.FetchAll();
+    if (!result || result->empty()) {
+      return Outcome::Aborted;
+    }
+    if (ev.event_type == EventType::Read) {
+      ev.value = (*result)[0][0].ValueInt();
     }
+  } catch (mg::ClientException &e) {
+    return Outcome::ConnectionLost;
   }
+  return Outcome::Done;
 }
 
-void create_variables(MgClient &client, int64_t n_variables) {
+bool create_variables(MgClient &client, int64_t n_variables) {
   std::string create_stmt = "CREATE (n:KV {var: $var, val: $val});";
-  begin_transaction(client);
-  for (int64_t i = 0; i < n_variables; i++) {
-    mg::Map map{{"var", mg::Value(i)}, {"val", mg::Value(0)}};
-    assert(client.Execute(create_stmt, map.AsConstMap()));
+  try {
+    if (!client.Execute("BEGIN")) {
+      return false;
+    }
     client.DiscardAll();
+    for (int64_t i = 0; i < n_variables; i++) {
+      mg::Map map{{"var", mg::Value(i)}, {"val", mg::Value(0)}};
+      if (!client.Execute(create_stmt, map.AsConstMap())) {
+        return false;
+      }
+      client.DiscardAll();
+    }
+    if (!client.Execute("COMMIT")) {
+      return false;
+    }
+    client.DiscardAll();
+  } catch (mg::ClientException &e) {
+    return false;
   }
-  commit_transaction(client);
+  return true;
 }
 
-void drop_database(MgClient &client) {
-  assert(client.Execute("MATCH (n:KV) DELETE n;"));
-  client.DiscardAll();
+bool drop_database(MgClient &client) {
+  try {
+    if (!client.Execute("MATCH (n:KV) DELETE n;")) {
+      return false;
+    }
+    client.DiscardAll();
+  } catch (mg::ClientException &e) {
+    return false;
+  }
+  return true;
 }
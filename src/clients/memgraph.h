@@ -13,6 +13,9 @@ using MgClient = mg::Client;
 
 void init();
 std::unique_ptr<MgClient> new_client(rust::Str ip, uint16_t port);
-void exec_transaction(MgClient &client, rust::Vec<Event> &transaction);
-void create_variables(MgClient &client, int64_t n_variables);
-void drop_database(MgClient &client);
+Outcome begin_transaction(MgClient &client);
+Outcome commit_transaction(MgClient &client);
+Outcome rollback_transaction(MgClient &client);
+Outcome exec_event(MgClient &client, Event &ev);
+bool create_variables(MgClient &client, int64_t n_variables);
+bool drop_database(MgClient &client);
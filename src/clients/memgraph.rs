@@ -1,15 +1,14 @@
 use std::collections::HashMap;
-use std::fs;
 use std::net::SocketAddr;
-use std::path::Path;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::thread::spawn;
 
+use crate::db::clock::Clock;
 use crate::db::cluster::{Cluster, ClusterNode, Node};
-use crate::db::history::{HistParams, Transaction};
-
-use clap::{App, Arg};
+use crate::db::error::DbcopError;
+use crate::db::history::{AbortReason, HistParams, Transaction};
+use crate::db::retry::{Deadline, RetryPolicy};
 
 use indicatif::{MultiProgress, ProgressBar};
 
@@ -26,6 +25,12 @@ mod ffi {
         value: i64,
     }
 
+    enum Outcome {
+        Done,
+        Aborted,
+        ConnectionLost,
+    }
+
     unsafe extern "C++" {
         include!("dbcop/src/clients/memgraph.h");
 
@@ -33,10 +38,75 @@ mod ffi {
 
         fn init();
         fn new_client(ip: &str, port: u16) -> UniquePtr<MgClient>;
-        fn exec_transaction(client: Pin<&mut MgClient>, transaction: &mut Vec<Event>);
-        fn create_variables(client: Pin<&mut MgClient>, n_variables: i64);
-        fn drop_database(client: Pin<&mut MgClient>);
+        fn begin_transaction(client: Pin<&mut MgClient>) -> Outcome;
+        fn commit_transaction(client: Pin<&mut MgClient>) -> Outcome;
+        fn rollback_transaction(client: Pin<&mut MgClient>) -> Outcome;
+        fn exec_event(client: Pin<&mut MgClient>, ev: &mut Event) -> Outcome;
+        fn create_variables(client: Pin<&mut MgClient>, n_variables: i64) -> bool;
+        fn drop_database(client: Pin<&mut MgClient>) -> bool;
+    }
+}
+
+fn connect(addr: SocketAddr) -> Result<cxx::UniquePtr<ffi::MgClient>, DbcopError> {
+    let client = ffi::new_client(addr.ip().to_string().as_str(), addr.port());
+    if client.is_null() {
+        Err(DbcopError::connection(addr, "couldn't connect"))
+    } else {
+        Ok(client)
+    }
+}
+
+fn abort_reason(outcome: ffi::Outcome) -> Option<AbortReason> {
+    match outcome {
+        ffi::Outcome::Done => None,
+        // conflicting transactions are the only failure memgraph reports without dropping the
+        // connection
+        ffi::Outcome::Aborted => Some(AbortReason::Serialization),
+        _ => Some(AbortReason::ConnectionLost),
+    }
+}
+
+/// Executes `transaction` once, returns why it aborted or `None` if it committed.
+fn exec_transaction(
+    client: &mut cxx::UniquePtr<ffi::MgClient>,
+    transaction: &mut Transaction,
+    deadline: Deadline,
+) -> Option<AbortReason> {
+    if let Some(reason) = abort_reason(ffi::begin_transaction(client.as_mut().unwrap())) {
+        return Some(reason);
     }
+
+    for event in transaction.events.iter_mut() {
+        let reason = if deadline.expired() {
+            Some(AbortReason::Timeout)
+        } else {
+            let mut cxxev = ffi::Event {
+                event_type: match event.write {
+                    true => ffi::EventType::Write,
+                    false => ffi::EventType::Read,
+                },
+                key: event.variable as i64,
+                value: event.value as i64,
+            };
+            let reason = abort_reason(ffi::exec_event(client.as_mut().unwrap(), &mut cxxev));
+            event.value = cxxev.value as usize;
+            reason
+        };
+
+        if let Some(reason) = reason {
+            if reason != AbortReason::ConnectionLost {
+                ffi::rollback_transaction(client.as_mut().unwrap());
+            }
+            return Some(reason);
+        }
+        event.success = true;
+    }
+
+    if deadline.expired() {
+        ffi::rollback_transaction(client.as_mut().unwrap());
+        return Some(AbortReason::Timeout);
+    }
+    abort_reason(ffi::commit_transaction(client.as_mut().unwrap()))
 }
 
 #[derive(Debug)]
@@ -57,102 +127,34 @@ impl MemgraphNode {
 }
 
 impl ClusterNode for MemgraphNode {
-    fn exec_session(&self, hist: &mut Vec<Transaction>) {
+    fn exec_session(&self, hist: &mut Vec<Transaction>, retry: &RetryPolicy, clock: &Clock) -> Result<(), DbcopError> {
         let progress = self.progress.add(ProgressBar::new(hist.len() as u64));
-        // let reconnect = || loop {
-        //     if let Ok(conn) = Connection::connect(&ConnectParams {
-        //         address: Some(self.addr.ip().to_string()),
-        //         port: self.addr.port(),
-        //         sslmode: SSLMode::Disable,
-        //         lazy: false,
-        //         ..Default::default()
-        //     }) {
-        //         return conn;
-        //     }
-        // };
-        // let mut conn = reconnect();
-
-        let mut client = ffi::new_client(self.addr.ip().to_string().as_str(), self.addr.port());
-
-        for txn in progress.wrap_iter(hist.iter_mut()) {
-            let mut cxxtxn = txn
-                .events
-                .iter()
-                .map(|ev| ffi::Event {
-                    event_type: match ev.write {
-                        true => ffi::EventType::Write,
-                        false => ffi::EventType::Read,
+
+        // dropped after the connection was lost, reconnected by the next attempt
+        let mut client = Some(connect(self.addr)?);
+
+        let mut executed = Vec::with_capacity(hist.len());
+        for mut transaction in progress.wrap_iter(hist.drain(..)) {
+            let aborted = retry.run(&mut transaction, clock, |transaction, deadline| {
+                let mgclient = match client.as_mut() {
+                    Some(mgclient) => mgclient,
+                    None => match connect(self.addr) {
+                        Ok(mgclient) => client.insert(mgclient),
+                        Err(_) => return Ok(Some(AbortReason::ConnectionLost)),
                     },
-                    key: ev.variable as i64,
-                    value: ev.value as i64,
-                })
-                .collect();
-
-            ffi::exec_transaction(client.as_mut().unwrap(), &mut cxxtxn);
-            txn.success = true;
-            txn.events.iter_mut().zip(cxxtxn.iter()).for_each(|(ev, cxxev)| {
-                ev.success = true;
-                ev.value = cxxev.value as usize;
-            });
+                };
+                let reason = exec_transaction(mgclient, transaction, deadline);
+                if reason == Some(AbortReason::ConnectionLost) {
+                    client = None;
+                }
+                Ok(reason)
+            })?;
+            executed.extend(aborted);
+            executed.push(transaction);
         }
+        *hist = executed;
 
-        // for transaction in progress.wrap_iter(hist.iter_mut()) {
-        //     loop {
-        //         let mut success = true;
-        //         for event in transaction.events.iter_mut() {
-        //             let params = HashMap::from([
-        //                 ("var".to_string(), QueryParam::Int(event.variable as i64)),
-        //                 ("val".to_string(), QueryParam::Int(event.value as i64)),
-        //             ]);
-        //             if event.write {
-        //                 if let Err(e) =
-        //                     conn.execute("MATCH (n:KV {var: $var}) SET n.val = $val", Some(&params))
-        //                 {
-        //                     success = false;
-        //                     // eprintln!("{}:{:?}:{:?}", line!(), e, conn.status());
-        //                     break;
-        //                 }
-
-        //                 if let Err(e) = conn.fetchall() {
-        //                     success = false;
-        //                     // eprintln!("{}:{:?}:{:?}:{:?}:{}", line!(), e, status, conn.status(), conn.lazy());
-        //                     break;
-        //                 }
-        //             } else {
-        //                 if let Err(e) =
-        //                     conn.execute("MATCH (n:KV {var: $var}) RETURN n.val", Some(&params))
-        //                 {
-        //                     success = false;
-        //                     // eprintln!("{}:{:?}", line!(), e);
-        //                     break;
-        //                 }
-
-        //                 match conn.fetchall() {
-        //                     Ok(rows) => {
-        //                         event.success = true;
-        //                         match rows[0].values[0] {
-        //                             rsmgclient::Value::Int(val) => event.value = val as usize,
-        //                             _ => unreachable!(),
-        //                         }
-        //                     }
-        //                     Err(e) => {
-        //                         success = false;
-        //                         // eprintln!("{}:{:?}", line!(), e);
-        //                         break;
-        //                     }
-        //                 }
-        //             }
-        //         }
-
-        //         if !success {
-        //             assert!(conn.status() == ConnectionStatus::Bad);
-        //             reconnect();
-        //         } else if conn.commit().is_ok() {
-        //             transaction.success = true;
-        //             break;
-        //         }
-        //     }
-        // }
+        Ok(())
     }
 }
 
@@ -167,17 +169,16 @@ impl MemgraphCluster {
         )
     }
 
-    fn create_table(&self) -> bool {
-        true
+    fn create_table(&self) -> Result<(), DbcopError> {
+        Ok(())
     }
 
-    fn create_variables(&self, n_variable: usize) {
-        let mut client = ffi::new_client(
-            self.0[0].addr.ip().to_string().as_str(),
-            self.0[0].addr.port(),
-        );
+    fn create_variables(&self, n_variable: usize) -> Result<(), DbcopError> {
+        let mut client = connect(self.get_addr(0)?)?;
 
-        ffi::create_variables(client.as_mut().unwrap(), n_variable as i64);
+        if !ffi::create_variables(client.as_mut().unwrap(), n_variable as i64) {
+            return Err(DbcopError::setup("couldn't create variables"));
+        }
 
         // let mut conn = self
         //     .get_memgraph_addr(0)
@@ -198,15 +199,15 @@ impl MemgraphCluster {
         // }
 
         // conn.commit().unwrap();
+        Ok(())
     }
 
-    fn drop_database(&self) {
-        let mut client = ffi::new_client(
-            self.0[0].addr.ip().to_string().as_str(),
-            self.0[0].addr.port(),
-        );
+    fn drop_database(&self) -> Result<(), DbcopError> {
+        let mut client = connect(self.get_addr(0)?)?;
 
-        ffi::drop_database(client.as_mut().unwrap());
+        if !ffi::drop_database(client.as_mut().unwrap()) {
+            return Err(DbcopError::setup("couldn't drop variables"));
+        }
 
         // let mut conn = self
         //     .get_memgraph_addr(0)
@@ -217,6 +218,11 @@ impl MemgraphCluster {
         // conn.execute_without_results("MATCH (n:KV) DELETE n")
         //     .unwrap();
         // conn.commit().unwrap();
+        Ok(())
+    }
+
+    fn get_addr(&self, i: usize) -> Result<SocketAddr, DbcopError> {
+        self.0.get(i).map(|node| node.addr).ok_or_else(|| DbcopError::setup("no node given"))
     }
 
     // fn get_memgraph_addr(&self, i: usize) -> Option<ConnectParams> {
@@ -233,7 +239,7 @@ impl Cluster<MemgraphNode> for MemgraphCluster {
     fn n_node(&self) -> usize {
         self.0.len()
     }
-    fn setup(&self) -> bool {
+    fn setup(&self) -> Result<(), DbcopError> {
         ffi::init();
         self.create_table()
     }
@@ -243,55 +249,18 @@ impl Cluster<MemgraphNode> for MemgraphCluster {
     fn get_cluster_node(&self, id: usize) -> MemgraphNode {
         MemgraphNode::new(self.get_node(id), self)
     }
-    fn setup_test(&mut self, p: &HistParams) {
-        self.create_variables(p.get_n_variable());
+    fn setup_test(&mut self, p: &HistParams) -> Result<(), DbcopError> {
+        self.create_variables(p.get_n_variable())?;
         std::thread::sleep(std::time::Duration::from_millis(1000));
 
         let progress = self.1.clone();
         spawn(move || progress.join());
+        Ok(())
     }
-    fn cleanup(&self) {
-        self.drop_database();
+    fn cleanup(&self) -> Result<(), DbcopError> {
+        self.drop_database()
     }
     fn info(&self) -> String {
         "memgraph".to_string()
     }
 }
-
-// fn main() {
-//     let matches = App::new("memgraph")
-//         .version("1.0")
-//         .author("Ranadeep")
-//         .about("executes histories on memgraph")
-//         .arg(
-//             Arg::with_name("hist_dir")
-//                 .long("dir")
-//                 .short("d")
-//                 .takes_value(true)
-//                 .required(true),
-//         )
-//         .arg(
-//             Arg::with_name("hist_out")
-//                 .long("out")
-//                 .short("o")
-//                 .takes_value(true)
-//                 .required(true),
-//         )
-//         .arg(
-//             Arg::with_name("ip:port")
-//                 .help("DB addr")
-//                 .required(true),
-//         )
-//         .get_matches();
-
-//     let hist_dir = Path::new(matches.value_of("hist_dir").unwrap());
-//     let hist_out = Path::new(matches.value_of("hist_out").unwrap());
-
-//     fs::create_dir_all(hist_out).expect("couldn't create directory");
-
-//     let ips: Vec<_> = matches.values_of("ip:port").unwrap().collect();
-
-//     let mut cluster = MemgraphCluster::new(&ips);
-
-//     cluster.execute_all(hist_dir, hist_out, 100);
-// }
@@ -1,21 +1,26 @@
+mod abort;
+mod isolation;
+mod pg_wire;
+mod mysql_wire;
 mod dgraph;
 mod postgres;
-mod postgres_ser;
 mod tidb;
+mod cockroachdb;
 mod yugabyte;
-mod yugabyte_ser;
 mod memgraph;
 mod dyncluster;
-mod galera;
 mod mysql;
+mod in_memory;
+mod antidote_pb;
+mod antidote;
 
 pub use dgraph::DGraphCluster;
 pub use crate::clients::postgres::PostgresCluster;
-pub use postgres_ser::PostgresCluster as PostgresSERCluster;
 pub use tidb::TiDBCluster;
+pub use cockroachdb::CockroachCluster;
 pub use yugabyte::YugabyteCluster;
-pub use yugabyte_ser::YugabyteCluster as YugabyteSERCluster;
 pub use memgraph::MemgraphCluster;
-pub use galera::GaleraCluster;
 pub use crate::clients::mysql::MySQLCluster;
 pub use dyncluster::{DynCluster, DynNode};
+pub use in_memory::{InMemoryCluster, Mode as InMemoryMode};
+pub use antidote::AntidoteCluster;
@@ -1,129 +1,87 @@
-use crate::db::cluster::{Cluster, ClusterNode, Node};
-use crate::db::history::{HistParams, Transaction};
+use crate::db::cluster::{Cluster, Init, Isolation, Node};
+use crate::db::error::DbcopError;
+use crate::db::history::HistParams;
 
-use mysql::{Conn, TxOpts, prelude::*};
+use super::mysql_wire::MySQLWireNode;
 
-#[derive(Debug)]
-pub struct MySQLNode {
-    addr: String,
-}
-
-impl From<Node> for MySQLNode {
-    fn from(node: Node) -> Self {
-        MySQLNode {
-            addr: format!("mysql://{}@{}", "root", node.addr),
-        }
-    }
-}
-
-impl ClusterNode for MySQLNode {
-    fn exec_session(&self, hist: &mut Vec<Transaction>) {
-        let mut conn = Conn::new(self.addr.as_str()).unwrap();
-        let txnopts = TxOpts::default()
-            .set_isolation_level(Some(mysql::IsolationLevel::Serializable))
-            .set_access_mode(Some(mysql::AccessMode::ReadWrite))
-            .set_with_consistent_snapshot(true);
-        let read_stmt = conn.prep("SELECT * FROM dbcop.variables WHERE var=?").unwrap();
-        let write_stmt = conn.prep("UPDATE dbcop.variables SET val=? WHERE var=?").unwrap();
-
-        for transaction in hist.iter_mut() {
-            while !transaction.success {
-                let mut sqltxn = conn.start_transaction(txnopts).unwrap();
-                transaction.success = true;
-
-                for event in transaction.events.iter_mut() {
-                    if event.write {
-                        if let Err(_) = sqltxn.exec_drop(&write_stmt, (event.value, event.variable)) {
-                            transaction.success = false;
-                            break;
-                        }
-                        event.success = true;
-                    } else {
-                        match sqltxn.exec_first(&read_stmt, (event.variable,)) {
-                            Ok(result) => {
-                                let mut row: mysql::Row = result.unwrap();
-                                event.value = row.take("val").unwrap();
-                                event.success = true;
-                            },
-                            Err(_) => {
-                                transaction.success = false;
-                                break;
-                            }
-                        }
-                    }
-                }
-
-                transaction.success = transaction.success && sqltxn.commit().is_ok();
-            }
-        }
-    }
-}
+use mysql::{Conn, prelude::*};
 
+/// MySQL, or a database run on it under its own name, which is recorded in `info`.
 #[derive(Debug)]
-pub struct MySQLCluster(Vec<Node>);
+pub struct MySQLCluster(Vec<Node>, Isolation, Init, &'static str);
 
 impl MySQLCluster {
-    pub fn new(ips: &Vec<&str>) -> Self {
-        MySQLCluster(MySQLCluster::node_vec(ips))
+    pub fn new(ips: &Vec<&str>, isolation: Isolation, init: Init) -> Self {
+        MySQLCluster(MySQLCluster::node_vec(ips), isolation, init, "MySQL")
+    }
+
+    /// A Galera cluster of MySQL or MariaDB nodes.
+    pub fn galera(ips: &Vec<&str>, isolation: Isolation, init: Init) -> Self {
+        MySQLCluster(MySQLCluster::node_vec(ips), isolation, init, "Galera")
     }
 
-    fn create_table(&self) -> bool {
-        let addr = self.get_mysql_addr(0).unwrap();
-        let mut conn = mysql::Conn::new(addr.as_str()).unwrap();
+    fn create_table(&self) -> Result<(), DbcopError> {
+        let mut conn = self.connect()?;
 
-        conn.exec_drop("CREATE DATABASE IF NOT EXISTS dbcop", ()).unwrap();
-        conn.exec_drop("DROP TABLE IF EXISTS dbcop.variables", ()).unwrap();
+        conn.exec_drop("CREATE DATABASE IF NOT EXISTS dbcop", ()).map_err(DbcopError::setup)?;
+        conn.exec_drop("DROP TABLE IF EXISTS dbcop.variables", ()).map_err(DbcopError::setup)?;
         conn.exec_drop(
             "CREATE TABLE IF NOT EXISTS dbcop.variables (var BIGINT(64) UNSIGNED NOT NULL PRIMARY KEY, val BIGINT(64) UNSIGNED NOT NULL)", ()
-        ).unwrap();
-        true
+        ).map_err(DbcopError::setup)?;
+        Ok(())
     }
 
-    fn create_variables(&self, n_variable: usize) {
-        let addr = self.get_mysql_addr(0).unwrap();
-        let mut conn = mysql::Conn::new(addr.as_str()).unwrap();
+    fn create_variables(&self, n_variable: usize) -> Result<(), DbcopError> {
+        if self.2 == Init::Lazy {
+            // variables are created by their first write
+            return Ok(());
+        }
+
+        let mut conn = self.connect()?;
 
         conn.exec_batch(
             "INSERT INTO dbcop.variables (var, val) values (?, 0)",
             (0..n_variable).map(|v| (v,))
-        ).unwrap();
+        ).map_err(DbcopError::setup)
     }
 
-    fn drop_database(&self) {
-        let addr = self.get_mysql_addr(0).unwrap();
-        let mut conn = mysql::Conn::new(addr.as_str()).unwrap();
+    fn drop_database(&self) -> Result<(), DbcopError> {
+        let mut conn = self.connect()?;
 
-        conn.exec_drop("DROP DATABASE dbcop", ()).unwrap();
+        conn.exec_drop("DROP DATABASE dbcop", ()).map_err(DbcopError::setup)
+    }
+
+    fn connect(&self) -> Result<Conn, DbcopError> {
+        let addr = self.get_mysql_addr(0).ok_or_else(|| DbcopError::setup("no node given"))?;
+        Conn::new(addr.as_str()).map_err(|e| DbcopError::connection(self.0[0].addr, e))
     }
 
     fn get_mysql_addr(&self, i: usize) -> Option<String> {
-        match self.0.get(i) {
-            Some(ref node) => Some(format!("mysql://{}@{}", "root", node.addr)),
-            None => None,
-        }
+        self.0.get(i).map(|node| format!("mysql://{}@{}", "root", node.addr))
     }
 }
 
-impl Cluster<MySQLNode> for MySQLCluster {
+impl Cluster<MySQLWireNode> for MySQLCluster {
     fn n_node(&self) -> usize {
         self.0.len()
     }
-    fn setup(&self) -> bool {
+    fn setup(&self) -> Result<(), DbcopError> {
         self.create_table()
     }
     fn get_node(&self, id: usize) -> Node {
         self.0[id].clone()
     }
-    fn get_cluster_node(&self, id: usize) -> MySQLNode {
-        From::from(self.get_node(id))
+    fn get_cluster_node(&self, id: usize) -> MySQLWireNode {
+        let node = self.get_node(id);
+        MySQLWireNode::new(format!("mysql://{}@{}", "root", node.addr), &node, self.1, self.2)
     }
-    fn setup_test(&mut self, p: &HistParams) {
-        self.create_variables(p.get_n_variable());
+    fn setup_test(&mut self, p: &HistParams) -> Result<(), DbcopError> {
+        self.create_variables(p.get_n_variable())
     }
-    fn cleanup(&self) {
-        self.drop_database();
+    fn cleanup(&self) -> Result<(), DbcopError> {
+        self.drop_database()
     }
     fn info(&self) -> String {
-        "MySQL".to_string()
+        format!("{} ({}, {})", self.3, self.1, self.2)
     }
 }
@@ -0,0 +1,146 @@
+//! Sessions executed over the MySQL protocol, shared by the databases speaking it. The drivers
+//! only differ in how a node is reached and how the variables are set up.
+
+use std::net::SocketAddr;
+
+use crate::db::clock::Clock;
+use crate::db::cluster::{ClusterNode, Init, Isolation, Node};
+use crate::db::error::DbcopError;
+use crate::db::history::{AbortReason, Transaction};
+use crate::db::retry::{Deadline, RetryPolicy};
+
+use super::{abort, isolation};
+
+use mysql::{AccessMode, Conn, Opts, TxOpts, prelude::Queryable};
+
+#[derive(Debug)]
+pub struct MySQLWireNode {
+    addr: String,
+    node_addr: SocketAddr,
+    isolation: Isolation,
+    init: Init,
+}
+
+impl MySQLWireNode {
+    /// `node` reached by the connection url `addr`.
+    pub fn new(addr: String, node: &Node, isolation: Isolation, init: Init) -> Self {
+        MySQLWireNode {
+            addr,
+            node_addr: node.addr,
+            isolation,
+            init,
+        }
+    }
+
+    fn connect(&self, retry: &RetryPolicy) -> Result<Conn, DbcopError> {
+        let opts = Opts::from_url(&self.addr).map_err(DbcopError::setup)?;
+        let mut conn = Conn::new(opts).map_err(|e| DbcopError::connection(self.node_addr, e))?;
+        if let Some(timeout) = retry.statement_timeout {
+            // MariaDB (and Galera on it) limits statements by max_statement_time in seconds, MySQL
+            // and TiDB by max_execution_time in milliseconds
+            let version: Option<String> = conn
+                .query_first("SELECT VERSION()")
+                .map_err(|e| DbcopError::connection(self.node_addr, e))?;
+            let limit = if version.is_some_and(|version| version.contains("MariaDB")) {
+                format!("max_statement_time = {}", timeout.as_secs_f64())
+            } else {
+                format!("max_execution_time = {}", timeout.as_millis())
+            };
+            conn.query_drop(format!(
+                "SET SESSION {}, innodb_lock_wait_timeout = {}",
+                limit,
+                timeout.as_secs().max(1)
+            ))
+            .map_err(|e| DbcopError::connection(self.node_addr, e))?;
+        }
+        Ok(conn)
+    }
+}
+
+impl ClusterNode for MySQLWireNode {
+    fn exec_session(&self, hist: &mut Vec<Transaction>, retry: &RetryPolicy, clock: &Clock) -> Result<(), DbcopError> {
+        // dropped after the connection was lost, reconnected by the next attempt
+        let mut conn = Some(self.connect(retry)?);
+        let txnopts = TxOpts::default()
+            .set_isolation_level(Some(isolation::mysql(self.isolation)))
+            .set_access_mode(Some(AccessMode::ReadWrite))
+            .set_with_consistent_snapshot(true);
+
+        let mut executed = Vec::with_capacity(hist.len());
+        for mut transaction in hist.drain(..) {
+            let aborted = retry.run(&mut transaction, clock, |transaction, deadline| {
+                let sqlconn = match conn.as_mut() {
+                    Some(sqlconn) => sqlconn,
+                    None => match self.connect(retry) {
+                        Ok(sqlconn) => conn.insert(sqlconn),
+                        Err(_) => return Ok(Some(AbortReason::ConnectionLost)),
+                    },
+                };
+                let result = exec_transaction(sqlconn, txnopts, self.init, transaction, deadline);
+                if let Ok(Some(AbortReason::ConnectionLost)) = result {
+                    conn = None;
+                }
+                result
+            })?;
+            executed.extend(aborted);
+            executed.push(transaction);
+        }
+        *hist = executed;
+
+        Ok(())
+    }
+}
+
+/// Executes `transaction` once, returns why it aborted or `None` if it committed.
+fn exec_transaction(
+    conn: &mut Conn,
+    txnopts: TxOpts,
+    init: Init,
+    transaction: &mut Transaction,
+    deadline: Deadline,
+) -> Result<Option<AbortReason>, DbcopError> {
+    let mut sqltxn = match conn.start_transaction(txnopts) {
+        Ok(sqltxn) => sqltxn,
+        Err(e) => return Ok(Some(abort::mysql(&e))),
+    };
+
+    for event in transaction.events.iter_mut() {
+        if deadline.expired() {
+            return Ok(Some(AbortReason::Timeout));
+        }
+        if event.write {
+            let stmt = match init {
+                Init::Lazy => "INSERT INTO dbcop.variables (val, var) VALUES (?, ?) ON DUPLICATE KEY UPDATE val=VALUES(val)",
+                Init::Eager => "UPDATE dbcop.variables SET val=? WHERE var=?",
+            };
+            if let Err(e) = sqltxn.exec_drop(stmt, (event.value, event.variable)) {
+                return Ok(Some(abort::mysql(&e)));
+            }
+            event.success = true;
+        } else {
+            match sqltxn.exec_first::<mysql::Row, _, _>("SELECT * FROM dbcop.variables WHERE var=?", (event.variable,)) {
+                Ok(Some(mut row)) => {
+                    event.value = row.take("val").ok_or_else(|| DbcopError::protocol("row without val"))?;
+                    event.success = true;
+                },
+                // due to lazy insert, the variable may not exist
+                Ok(None) if init == Init::Lazy => {
+                    event.value = 0;
+                    event.success = true;
+                },
+                Ok(None) => {
+                    return Err(DbcopError::protocol(format!("variable {} does not exist", event.variable)))
+                },
+                Err(e) => return Ok(Some(abort::mysql(&e))),
+            }
+        }
+    }
+
+    if deadline.expired() {
+        return Ok(Some(AbortReason::Timeout));
+    }
+    match sqltxn.commit() {
+        Ok(_) => Ok(None),
+        Err(e) => Ok(Some(abort::mysql(&e))),
+    }
+}
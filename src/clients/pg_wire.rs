@@ -0,0 +1,154 @@
+//! Sessions executed over the PostgreSQL protocol, shared by the databases speaking it. The
+//! drivers only differ in how a node is reached and how the variables are set up.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use crate::db::clock::Clock;
+use crate::db::cluster::{ClusterNode, Init, Isolation, Node};
+use crate::db::error::DbcopError;
+use crate::db::history::{AbortReason, Transaction};
+use crate::db::retry::{Deadline, RetryPolicy};
+
+use super::{abort, isolation};
+
+use postgres::{Client, NoTls};
+
+use indicatif::{MultiProgress, ProgressBar};
+
+#[derive(Debug)]
+pub struct PgWireNode {
+    addr: String,
+    node_addr: SocketAddr,
+    isolation: Isolation,
+    init: Init,
+    progress: Option<Arc<MultiProgress>>,
+}
+
+impl PgWireNode {
+    /// `node` reached by the connection string `addr`, every session shows its progress in
+    /// `progress` if given.
+    pub fn new(
+        addr: String,
+        node: &Node,
+        isolation: Isolation,
+        init: Init,
+        progress: Option<Arc<MultiProgress>>,
+    ) -> Self {
+        PgWireNode {
+            addr,
+            node_addr: node.addr,
+            isolation,
+            init,
+            progress,
+        }
+    }
+
+    fn connect(&self, retry: &RetryPolicy) -> Result<Client, DbcopError> {
+        let mut conn = Client::connect(self.addr.as_str(), NoTls).map_err(|e| DbcopError::connection(self.node_addr, e))?;
+        if let Some(timeout) = retry.statement_timeout {
+            conn.batch_execute(&format!("SET statement_timeout = {}", timeout.as_millis()))
+                .map_err(|e| DbcopError::connection(self.node_addr, e))?;
+        }
+        Ok(conn)
+    }
+}
+
+impl ClusterNode for PgWireNode {
+    fn exec_session(&self, hist: &mut Vec<Transaction>, retry: &RetryPolicy, clock: &Clock) -> Result<(), DbcopError> {
+        let progress = match &self.progress {
+            Some(progress) => progress.add(ProgressBar::new(hist.len() as u64)),
+            None => ProgressBar::hidden(),
+        };
+        let mut conn = self.connect(retry)?;
+
+        let mut executed = Vec::with_capacity(hist.len());
+        for mut transaction in progress.wrap_iter(hist.drain(..)) {
+            let aborted = retry.run(&mut transaction, clock, |transaction, deadline| {
+                if conn.is_closed() {
+                    match self.connect(retry) {
+                        Ok(new_conn) => conn = new_conn,
+                        Err(_) => return Ok(Some(AbortReason::ConnectionLost)),
+                    }
+                }
+                exec_transaction(&mut conn, self.isolation, self.init, transaction, deadline)
+            })?;
+            executed.extend(aborted);
+            executed.push(transaction);
+        }
+        *hist = executed;
+
+        Ok(())
+    }
+}
+
+/// Executes `transaction` once, returns why it aborted or `None` if it committed.
+fn exec_transaction(
+    conn: &mut Client,
+    isolation: Isolation,
+    init: Init,
+    transaction: &mut Transaction,
+    deadline: Deadline,
+) -> Result<Option<AbortReason>, DbcopError> {
+    let mut sqltxn = match conn
+        .build_transaction()
+        .isolation_level(isolation::postgres(isolation))
+        .start()
+        {
+            Ok(txn) => txn,
+            Err(e) => return Ok(Some(abort::postgres(&e))),
+        };
+
+    for event in transaction.events.iter_mut() {
+        if deadline.expired() {
+            return Ok(Some(AbortReason::Timeout));
+        }
+        if event.write {
+            let stmt = match init {
+                Init::Lazy => {
+                    "INSERT INTO dbcop.variables (var, val) VALUES ($2, $1)
+                     ON CONFLICT (var) DO UPDATE SET val=$1"
+                }
+                Init::Eager => "UPDATE dbcop.variables SET val=$1 WHERE var=$2",
+            };
+            match sqltxn.execute(stmt, &[&(event.value as i64), &(event.variable as i64)]) {
+                Ok(_) => event.success = true,
+                // If an operation fails, then the whole transaction fails
+                Err(e) => return Ok(Some(abort::postgres(&e))),
+            }
+        } else {
+            match sqltxn.query(
+                "SELECT * FROM dbcop.variables WHERE var=$1",
+                &[&(event.variable as i64)],
+            ) {
+                Ok(result) => {
+                    match result.first() {
+                        Some(row) => {
+                            let value: i64 = row.get("val");
+                            event.value = value as usize;
+                        }
+                        // due to lazy insert, the variable may not exist
+                        // set result to the initial value
+                        None if init == Init::Lazy => event.value = 0,
+                        None => {
+                            return Err(DbcopError::protocol(format!(
+                                "variable {} does not exist",
+                                event.variable
+                            )))
+                        }
+                    }
+                    event.success = true;
+                }
+                Err(e) => return Ok(Some(abort::postgres(&e))),
+            }
+        }
+    }
+
+    if deadline.expired() {
+        return Ok(Some(AbortReason::Timeout));
+    }
+    match sqltxn.commit() {
+        Ok(_) => Ok(None),
+        Err(e) => Ok(Some(abort::postgres(&e))),
+    }
+}
@@ -1,210 +1,83 @@
-use std::path::Path;
+use crate::db::cluster::{Cluster, Init, Isolation, Node};
+use crate::db::error::DbcopError;
+use crate::db::history::HistParams;
 
-use crate::db::cluster::{Cluster, ClusterNode, Node};
-use crate::db::history::{HistParams, Transaction};
+use super::mysql_wire::MySQLWireNode;
 
-use std::fs;
-
-use clap::{App, Arg};
-
-use mysql::{AccessMode, Conn, IsolationLevel, Opts, TxOpts, prelude::Queryable};
+use mysql::{Conn, Opts, prelude::Queryable};
 
 #[derive(Debug)]
-pub struct TiDBNode {
-    addr: String,
-    id: usize,
-}
+pub struct TiDBCluster(Vec<Node>, Isolation, Init);
 
-impl From<Node> for TiDBNode {
-    fn from(node: Node) -> Self {
-        TiDBNode {
-            addr: format!("mysql://{}@{}", "root", node.addr),
-            id: node.id,
-        }
+impl TiDBCluster {
+    pub fn new(ips: &Vec<&str>, isolation: Isolation, init: Init) -> Self {
+        TiDBCluster(TiDBCluster::node_vec(ips), isolation, init)
     }
-}
 
-impl ClusterNode for TiDBNode {
-    fn exec_session(&self, hist: &mut Vec<Transaction>) {
-        match Conn::new(Opts::from_url(&self.addr).unwrap()) {
-            Ok(mut conn) => hist.iter_mut().for_each(|transaction| {
-                for mut sqltxn in conn.start_transaction(
-                    TxOpts::default()
-                        .set_with_consistent_snapshot(true)
-                        .set_isolation_level(Some(IsolationLevel::RepeatableRead))
-                        .set_access_mode(Some(AccessMode::ReadWrite)),
-                ) {
-                    transaction.events.iter_mut().for_each(|event| {
-                        if event.write {
-                            if let Err(_e) = sqltxn.exec_drop(
-                                "UPDATE dbcop.variables SET val=? WHERE var=?",
-                                (event.value, event.variable),
-                            ) {
-                                assert_eq!(event.success, false);
-                                // println!("WRITE ERR -- {:?}", _e);
-                            } else {
-                                event.success = true
-                            }
-                        } else {
-                            match sqltxn.exec_iter(
-                                "SELECT * FROM dbcop.variables WHERE var=?",
-                                (event.variable,),
-                            ) {
-                                Ok(mut result) => {
-                                    if let Some(q_result) = result.next() {
-                                        let mut row = q_result.unwrap();
-                                        if let Some(value) = row.take("val") {
-                                            event.value = value;
-                                            event.success = true;
-                                        }
-                                    } else {
-                                        // may be diverged
-                                        assert_eq!(event.success, false);
-                                    }
-                                }
-                                Err(_e) => {
-                                    // println!("READ ERR -- {:?}", _e);
-                                    assert_eq!(event.success, false);
-                                }
-                            }
-                        }
-                    });
-                    match sqltxn.commit() {
-                        Ok(_) => {
-                            transaction.success = true;
-                        }
-                        Err(_e) => {
-                            assert_eq!(transaction.success, false);
-                            // println!("{:?} -- COMMIT ERROR {}", transaction, _e);
-                        }
-                    }
-                }
-            }),
-            Err(_e) => {
-                hist.iter().for_each(|transaction| {
-                    assert_eq!(transaction.success, false);
-                });
-                // println!("CONNECTION ERROR {}", _e);}
-            }
-        }
+    fn create_table(&self) -> Result<(), DbcopError> {
+        let mut conn = self.connect()?;
+        conn.exec_drop("CREATE DATABASE IF NOT EXISTS dbcop", ()).map_err(DbcopError::setup)?;
+        conn.exec_drop("DROP TABLE IF EXISTS dbcop.variables", ()).map_err(DbcopError::setup)?;
+        conn.exec_drop(
+            "CREATE TABLE IF NOT EXISTS dbcop.variables (var BIGINT(64) UNSIGNED NOT NULL PRIMARY KEY, val BIGINT(64) UNSIGNED NOT NULL)", ()
+        ).map_err(DbcopError::setup)?;
+        conn.exec_drop("SET GLOBAL tidb_txn_mode = 'optimistic'", ()).map_err(DbcopError::setup)?;
+        Ok(())
     }
-}
 
-#[derive(Debug)]
-pub struct TiDBCluster(Vec<Node>);
-
-impl TiDBCluster {
-    fn new(ips: &Vec<&str>) -> Self {
-        TiDBCluster(TiDBCluster::node_vec(ips))
-    }
+    fn create_variables(&self, n_variable: usize) -> Result<(), DbcopError> {
+        if self.2 == Init::Lazy {
+            // variables are created by their first write
+            return Ok(());
+        }
 
-    fn create_table(&self) -> bool {
-        match self.get_mysql_addr(0) {
-            Some(ip) => Conn::new(Opts::from_url(ip.as_str()).unwrap())
-                .and_then(|mut pool| {
-                    pool.exec_drop("CREATE DATABASE IF NOT EXISTS dbcop", ()).unwrap();
-                    pool.exec_drop("DROP TABLE IF EXISTS dbcop.variables", ()).unwrap();
-                    pool.exec_drop(
-                        "CREATE TABLE IF NOT EXISTS dbcop.variables (var BIGINT(64) UNSIGNED NOT NULL PRIMARY KEY, val BIGINT(64) UNSIGNED NOT NULL)", ()
-                    ).unwrap();
-                    pool.exec_drop("SET GLOBAL tidb_txn_mode = 'optimistic'", ()).unwrap();
-                    // conn.query("USE dbcop").unwrap();
-                    Ok(true)
-                }).expect("problem creating database"),
-            _ => false,
+        let mut conn = self.connect()?;
+        let stmt = conn
+            .prep("INSERT INTO dbcop.variables (var, val) values (?, 0)")
+            .map_err(DbcopError::setup)?;
+        for variable in 0..n_variable {
+            conn.exec_drop(&stmt, (variable,)).map_err(DbcopError::setup)?;
         }
+        Ok(())
     }
 
-    fn create_variables(&self, n_variable: usize) {
-        if let Some(ip) = self.get_mysql_addr(0) {
-            if let Ok(mut conn) = Conn::new(Opts::from_url(ip.as_str()).unwrap()) {
-                for stmt in conn
-                    .prep("INSERT INTO dbcop.variables (var, val) values (?, 0)")
-                    .into_iter()
-                {
-                    (0..n_variable).for_each(|variable| {
-                        conn.exec_drop(&stmt, (variable,)).unwrap();
-                    });
-                }
-            }
-        }
+    fn drop_database(&self) -> Result<(), DbcopError> {
+        let mut conn = self.connect()?;
+        conn.exec_drop("DROP DATABASE dbcop", ()).map_err(DbcopError::setup)
     }
 
-    fn drop_database(&self) {
-        if let Some(ip) = self.get_mysql_addr(0) {
-            if let Ok(mut conn) = Conn::new(Opts::from_url(ip.as_str()).unwrap()) {
-                conn.exec_drop("DROP DATABASE dbcop", ()).unwrap();
-            }
-        }
+    fn connect(&self) -> Result<Conn, DbcopError> {
+        let ip = self.get_mysql_addr(0).ok_or_else(|| DbcopError::setup("no node given"))?;
+        let opts = Opts::from_url(ip.as_str()).map_err(DbcopError::setup)?;
+        Conn::new(opts).map_err(|e| DbcopError::connection(self.0[0].addr, e))
     }
 
     fn get_mysql_addr(&self, i: usize) -> Option<String> {
-        match self.0.get(i) {
-            Some(ref node) => Some(format!("mysql://{}@{}", "root", node.addr)),
-            None => None,
-        }
+        self.0.get(i).map(|node| format!("mysql://{}@{}", "root", node.addr))
     }
 }
 
-impl Cluster<TiDBNode> for TiDBCluster {
+impl Cluster<MySQLWireNode> for TiDBCluster {
     fn n_node(&self) -> usize {
         self.0.len()
     }
-    fn setup(&self) -> bool {
+    fn setup(&self) -> Result<(), DbcopError> {
         self.create_table()
     }
     fn get_node(&self, id: usize) -> Node {
         self.0[id].clone()
     }
-    fn get_cluster_node(&self, id: usize) -> TiDBNode {
-        From::from(self.get_node(id))
+    fn get_cluster_node(&self, id: usize) -> MySQLWireNode {
+        let node = self.get_node(id);
+        MySQLWireNode::new(format!("mysql://{}@{}", "root", node.addr), &node, self.1, self.2)
     }
-    fn setup_test(&mut self, p: &HistParams) {
-        self.create_variables(p.get_n_variable());
+    fn setup_test(&mut self, p: &HistParams) -> Result<(), DbcopError> {
+        self.create_variables(p.get_n_variable())
     }
-    fn cleanup(&self) {
-        self.drop_database();
+    fn cleanup(&self) -> Result<(), DbcopError> {
+        self.drop_database()
     }
     fn info(&self) -> String {
-        "Galera".to_string()
+        format!("TiDB ({}, {})", self.1, self.2)
     }
 }
-
-// fn main() {
-//     let matches = App::new("TiDB")
-//         .version("1.0")
-//         .author("Ranadeep")
-//         .about("executes histories on TiDB")
-//         .arg(
-//             Arg::with_name("hist_dir")
-//                 .long("dir")
-//                 .short("d")
-//                 .takes_value(true)
-//                 .required(true),
-//         )
-//         .arg(
-//             Arg::with_name("hist_out")
-//                 .long("out")
-//                 .short("o")
-//                 .takes_value(true)
-//                 .required(true),
-//         )
-//         .arg(
-//             Arg::with_name("ips")
-//                 .help("Cluster ips")
-//                 .multiple(true)
-//                 .required(true),
-//         )
-//         .get_matches();
-
-//     let hist_dir = Path::new(matches.value_of("hist_dir").unwrap());
-//     let hist_out = Path::new(matches.value_of("hist_out").unwrap());
-
-//     fs::create_dir_all(hist_out).expect("couldn't create directory");
-
-//     let ips: Vec<_> = matches.values_of("ips").unwrap().collect();
-
-//     let mut cluster = TiDBCluster::new(&ips);
-
-//     cluster.execute_all(hist_dir, hist_out, 500);
-// }
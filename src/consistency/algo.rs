@@ -0,0 +1,399 @@
+use std::collections::{HashMap, HashSet};
+
+use super::util::{AtomicHistory, BitSet, DiGraph, Numbering, TransactionId};
+
+/// Checks read committed (Adya's PL-2): aborted and intermediate reads (G1a, G1b) are rejected
+/// while building the history, and session order, write-read relation and some write-write
+/// relation must be acyclic (G0, G1c). Ordering the writers of every variable along a topological
+/// order of session order and write-read relation adds no cycle, so the history is read committed
+/// iff session order and write-read relation are acyclic.
+pub fn check_read_committed(history: &AtomicHistory) -> bool {
+    let numbering = history.numbering();
+    history.so_wr_graph(&numbering).is_acyclic()
+}
+
+/// Checks read atomic, i.e. the absence of fractured reads: whenever `t3` reads `x` from `t1`
+/// and a transaction `t2` writing `x` is directly before `t3` in session order or write-read
+/// relation, then `t2` must be ordered before `t1`. The history is read atomic iff these orderings
+/// together with session order and write-read relation are acyclic.
+pub fn check_read_atomic(history: &AtomicHistory) -> bool {
+    let numbering = history.numbering();
+    let mut graph = history.so_wr_graph(&numbering);
+
+    for (s, session) in history.sessions.iter().enumerate().skip(1) {
+        // variable -> last transaction of the session writing it so far
+        let mut last_write: HashMap<usize, TransactionId> = HashMap::new();
+        for i in 0..session.len() {
+            let t3 = (s, i);
+            let txn = history.txn(t3);
+            for (var, &t1) in txn.read.iter() {
+                let so = last_write.get(var).copied();
+                let wr = txn.read.values().copied().filter(|&t2| history.txn(t2).write.contains(var));
+                for t2 in so.into_iter().chain(wr) {
+                    if t2 != t1 {
+                        graph.add_edge(numbering.index(t2), numbering.index(t1));
+                    }
+                }
+            }
+            for &var in txn.write.iter() {
+                last_write.insert(var, t3);
+            }
+        }
+    }
+
+    graph.is_acyclic()
+}
+
+/// Checks causal consistency: whenever `t1` writes `x` and is causally before `t2`, which reads
+/// `x` from `t3`, then `t1` must be ordered before `t3`. The history is causal iff these
+/// orderings together with session order and write-read relation are acyclic.
+pub fn check_causal(history: &AtomicHistory) -> bool {
+    let numbering = history.numbering();
+    let mut graph = history.so_wr_graph(&numbering);
+
+    let order = match graph.topological_order() {
+        Some(order) => order,
+        None => return false,
+    };
+    let causal = graph.transitive_closure(&order);
+    let writers = history.writers();
+
+    for t2 in history.ids() {
+        for (var, &t3) in history.txn(t2).read.iter() {
+            for &t1 in writers[var].iter() {
+                if t1 != t3 && t1 != t2 && causal[numbering.index(t2)].contains(numbering.index(t1)) {
+                    graph.add_edge(numbering.index(t1), numbering.index(t3));
+                }
+            }
+        }
+    }
+
+    graph.is_acyclic()
+}
+
+/// Checks parallel snapshot isolation: there must be a causal order in which the writers of every
+/// variable are totally ordered and every read observes the causally latest write of the variable.
+/// Whenever `t1` writes `x` and is causally before `t3`, which reads `x` from `t2`, then `t1` must
+/// be ordered before `t2`. Writers left unordered by these orderings are ordered both ways in turn.
+pub fn check_parallel_snapshot_isolation(history: &AtomicHistory) -> bool {
+    let numbering = history.numbering();
+    let graph = history.so_wr_graph(&numbering);
+    let writers = history.writers();
+
+    ParallelSnapshotIsolationHistory {
+        history,
+        numbering: &numbering,
+        writers: &writers,
+    }
+    .search(graph)
+}
+
+struct ParallelSnapshotIsolationHistory<'a> {
+    history: &'a AtomicHistory,
+    numbering: &'a Numbering,
+    writers: &'a HashMap<usize, Vec<TransactionId>>,
+}
+
+impl<'a> ParallelSnapshotIsolationHistory<'a> {
+    /// Adds the orderings implied by the causal order until none is missing, returns the causal
+    /// order, or `None` if it is cyclic.
+    fn saturate(&self, graph: &mut DiGraph) -> Option<Vec<BitSet>> {
+        loop {
+            let order = graph.topological_order()?;
+            let causal = graph.transitive_closure(&order);
+            let mut changed = false;
+
+            for t3 in self.history.ids() {
+                let i3 = self.numbering.index(t3);
+                for (var, &t2) in self.history.txn(t3).read.iter() {
+                    let i2 = self.numbering.index(t2);
+                    for &t1 in self.writers[var].iter() {
+                        let i1 = self.numbering.index(t1);
+                        if t1 != t2 && t1 != t3 && causal[i3].contains(i1) && !causal[i2].contains(i1) {
+                            graph.add_edge(i1, i2);
+                            changed = true;
+                        }
+                    }
+                }
+            }
+
+            if !changed {
+                return Some(causal);
+            }
+        }
+    }
+
+    fn search(&self, mut graph: DiGraph) -> bool {
+        let causal = match self.saturate(&mut graph) {
+            Some(causal) => causal,
+            None => return false,
+        };
+
+        let unordered = self.writers.values().find_map(|writers| {
+            writers.iter().enumerate().find_map(|(i, &w1)| {
+                let i1 = self.numbering.index(w1);
+                writers[i + 1..].iter().find_map(|&w2| {
+                    let i2 = self.numbering.index(w2);
+                    (!causal[i2].contains(i1) && !causal[i1].contains(i2)).then_some((i1, i2))
+                })
+            })
+        });
+
+        match unordered {
+            None => true,
+            Some((i1, i2)) => {
+                let mut before = graph.clone();
+                before.add_edge(i1, i2);
+                if self.search(before) {
+                    return true;
+                }
+                graph.add_edge(i2, i1);
+                self.search(graph)
+            }
+        }
+    }
+}
+
+/// Search for a linearization of the sessions of a history, where each session contributes a
+/// fixed number of steps. A frontier stores how many steps of each session are already taken.
+pub trait ConstrainedLinearization {
+    fn n_session(&self) -> usize;
+    fn n_step(&self, session: usize) -> usize;
+    fn initial_frontier(&self) -> Vec<usize>;
+    fn allowed(&self, frontier: &[usize], session: usize) -> bool;
+
+    fn linearizable(&self) -> bool {
+        let initial = self.initial_frontier();
+        let mut seen = HashSet::new();
+        seen.insert(initial.clone());
+        let mut stack = vec![initial];
+
+        while let Some(frontier) = stack.pop() {
+            if (0..self.n_session()).all(|s| frontier[s] == self.n_step(s)) {
+                return true;
+            }
+            for s in 0..self.n_session() {
+                if frontier[s] < self.n_step(s) && self.allowed(&frontier, s) {
+                    let mut next = frontier.clone();
+                    next[s] += 1;
+                    if seen.insert(next.clone()) {
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+
+        false
+    }
+}
+
+pub struct SerializableHistory<'a> {
+    history: &'a AtomicHistory,
+    writers: HashMap<usize, Vec<TransactionId>>,
+    readers: HashMap<(usize, TransactionId), Vec<TransactionId>>,
+}
+
+impl<'a> SerializableHistory<'a> {
+    pub fn new(history: &'a AtomicHistory) -> Self {
+        SerializableHistory {
+            history,
+            writers: history.writers(),
+            readers: history.readers(),
+        }
+    }
+
+    fn executed(frontier: &[usize], id: TransactionId) -> bool {
+        id.1 < frontier[id.0]
+    }
+}
+
+impl<'a> ConstrainedLinearization for SerializableHistory<'a> {
+    fn n_session(&self) -> usize {
+        self.history.n_session()
+    }
+
+    fn n_step(&self, session: usize) -> usize {
+        self.history.sessions[session].len()
+    }
+
+    fn initial_frontier(&self) -> Vec<usize> {
+        let mut frontier = vec![0; self.n_session()];
+        frontier[0] = 1;
+        frontier
+    }
+
+    fn allowed(&self, frontier: &[usize], session: usize) -> bool {
+        let id = (session, frontier[session]);
+        let txn = self.history.txn(id);
+
+        // everything read by the transaction is already written
+        txn.read.values().all(|&w| Self::executed(frontier, w))
+            // and the transaction does not overwrite a value some pending transaction still reads
+            && txn.write.iter().all(|var| {
+                self.writers[var].iter().filter(|&&w| w != id && Self::executed(frontier, w)).all(|&w| {
+                    self.readers
+                        .get(&(*var, w))
+                        .is_none_or(|rs| rs.iter().all(|&r| r == id || Self::executed(frontier, r)))
+                })
+            })
+    }
+}
+
+/// Serializability where a transaction is ordered after every transaction that completed before
+/// it was invoked. Transactions without timestamps are not constrained by real time.
+pub struct StrictSerializableHistory<'a> {
+    serializable: SerializableHistory<'a>,
+    /// transaction -> how many transactions of every session completed before its invocation
+    preceding: HashMap<TransactionId, Vec<usize>>,
+}
+
+impl<'a> StrictSerializableHistory<'a> {
+    pub fn new(history: &'a AtomicHistory) -> Self {
+        // earliest completion among a transaction and its successors in the session; a
+        // transaction precedes an invocation iff its entry is before it
+        let completed: Vec<Vec<u64>> = history
+            .sessions
+            .iter()
+            .map(|session| {
+                let mut earliest: Vec<u64> = session
+                    .iter()
+                    .rev()
+                    .scan(u64::MAX, |min, txn| {
+                        *min = txn.time.map_or(*min, |time| time.completed.min(*min));
+                        Some(*min)
+                    })
+                    .collect();
+                earliest.reverse();
+                earliest
+            })
+            .collect();
+
+        let preceding = history
+            .ids()
+            .filter_map(|id| history.txn(id).time.map(|time| (id, time.invoked)))
+            .map(|(id, invoked)| {
+                let counts = completed
+                    .iter()
+                    .map(|session| session.partition_point(|&c| c < invoked))
+                    .collect();
+                (id, counts)
+            })
+            .collect();
+
+        StrictSerializableHistory {
+            serializable: SerializableHistory::new(history),
+            preceding,
+        }
+    }
+}
+
+impl<'a> ConstrainedLinearization for StrictSerializableHistory<'a> {
+    fn n_session(&self) -> usize {
+        self.serializable.n_session()
+    }
+
+    fn n_step(&self, session: usize) -> usize {
+        self.serializable.n_step(session)
+    }
+
+    fn initial_frontier(&self) -> Vec<usize> {
+        self.serializable.initial_frontier()
+    }
+
+    fn allowed(&self, frontier: &[usize], session: usize) -> bool {
+        self.serializable.allowed(frontier, session)
+            && self
+                .preceding
+                .get(&(session, frontier[session]))
+                .is_none_or(|counts| counts.iter().zip(frontier).all(|(c, f)| c <= f))
+    }
+}
+
+/// Every transaction is split in a read step and a write step. Reads observe the state at the
+/// read step, writes are installed at the write step and, unless `prefix` is set, no two
+/// transactions writing the same variable may overlap. With `prefix` this checks prefix
+/// consistency: every transaction observes a prefix of one commit order.
+pub struct SnapshotIsolationHistory<'a> {
+    history: &'a AtomicHistory,
+    writers: HashMap<usize, Vec<TransactionId>>,
+    readers: HashMap<(usize, TransactionId), Vec<TransactionId>>,
+    prefix: bool,
+}
+
+impl<'a> SnapshotIsolationHistory<'a> {
+    pub fn new(history: &'a AtomicHistory) -> Self {
+        SnapshotIsolationHistory {
+            history,
+            writers: history.writers(),
+            readers: history.readers(),
+            prefix: false,
+        }
+    }
+
+    pub fn prefix(history: &'a AtomicHistory) -> Self {
+        SnapshotIsolationHistory {
+            prefix: true,
+            ..SnapshotIsolationHistory::new(history)
+        }
+    }
+
+    fn read_executed(frontier: &[usize], id: TransactionId) -> bool {
+        2 * id.1 < frontier[id.0]
+    }
+
+    fn write_executed(frontier: &[usize], id: TransactionId) -> bool {
+        2 * id.1 + 1 < frontier[id.0]
+    }
+}
+
+impl<'a> ConstrainedLinearization for SnapshotIsolationHistory<'a> {
+    fn n_session(&self) -> usize {
+        self.history.n_session()
+    }
+
+    fn n_step(&self, session: usize) -> usize {
+        2 * self.history.sessions[session].len()
+    }
+
+    fn initial_frontier(&self) -> Vec<usize> {
+        let mut frontier = vec![0; self.n_session()];
+        frontier[0] = 2;
+        frontier
+    }
+
+    fn allowed(&self, frontier: &[usize], session: usize) -> bool {
+        let id = (session, frontier[session] / 2);
+        let txn = self.history.txn(id);
+
+        if frontier[session].is_multiple_of(2) {
+            txn.read.values().all(|&w| Self::write_executed(frontier, w))
+        } else {
+            txn.write.iter().all(|var| {
+                self.writers[var].iter().filter(|&&w| w != id).all(|&w| {
+                    // no concurrent transaction writing the same variable
+                    (self.prefix || !Self::read_executed(frontier, w) || Self::write_executed(frontier, w))
+                        && (!Self::write_executed(frontier, w)
+                            || self.readers.get(&(*var, w)).is_none_or(|rs| {
+                                rs.iter().all(|&r| Self::read_executed(frontier, r))
+                            }))
+                })
+            })
+        }
+    }
+}
+
+pub fn check_prefix(history: &AtomicHistory) -> bool {
+    SnapshotIsolationHistory::prefix(history).linearizable()
+}
+
+pub fn check_snapshot_isolation(history: &AtomicHistory) -> bool {
+    SnapshotIsolationHistory::new(history).linearizable()
+}
+
+pub fn check_serializable(history: &AtomicHistory) -> bool {
+    SerializableHistory::new(history).linearizable()
+}
+
+pub fn check_strict_serializable(history: &AtomicHistory) -> bool {
+    StrictSerializableHistory::new(history).linearizable()
+}
@@ -0,0 +1,70 @@
+pub mod algo;
+pub mod util;
+pub mod witness;
+
+use std::fmt;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ValueEnum)]
+pub enum Consistency {
+    #[clap(name = "rc")]
+    ReadCommitted,
+    #[clap(name = "ra")]
+    ReadAtomic,
+    #[clap(name = "cc")]
+    Causal,
+    #[clap(name = "prefix")]
+    Prefix,
+    #[clap(name = "psi")]
+    ParallelSnapshotIsolation,
+    #[clap(name = "si")]
+    SnapshotIsolation,
+    #[clap(name = "ser")]
+    Serializable,
+    /// serializable in an order which extends the real-time order of transactions
+    #[clap(name = "strict-ser")]
+    StrictSerializable,
+}
+
+impl Consistency {
+    /// All levels, weakest first.
+    pub fn levels() -> &'static [Consistency] {
+        &[
+            Consistency::ReadCommitted,
+            Consistency::ReadAtomic,
+            Consistency::Causal,
+            Consistency::Prefix,
+            Consistency::ParallelSnapshotIsolation,
+            Consistency::SnapshotIsolation,
+            Consistency::Serializable,
+            Consistency::StrictSerializable,
+        ]
+    }
+
+    /// Whether every history satisfying `self` satisfies `other` as well. Levels are ordered by
+    /// strength, except that prefix consistency and parallel snapshot isolation are incomparable.
+    pub fn implies(&self, other: Consistency) -> bool {
+        match (self, other) {
+            (Consistency::ParallelSnapshotIsolation, Consistency::Prefix) => false,
+            _ => other <= *self,
+        }
+    }
+}
+
+impl fmt::Display for Consistency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let repr = match self {
+            Consistency::ReadCommitted => "rc",
+            Consistency::ReadAtomic => "ra",
+            Consistency::Causal => "cc",
+            Consistency::Prefix => "prefix",
+            Consistency::ParallelSnapshotIsolation => "psi",
+            Consistency::SnapshotIsolation => "si",
+            Consistency::Serializable => "ser",
+            Consistency::StrictSerializable => "strict-ser",
+        };
+        write!(f, "{}", repr)
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::db::history::Timestamps;
+
+/// `(session, position)` of a committed transaction in an `AtomicHistory`.
+/// Session `0` holds the initial transaction which writes `0` to every variable.
+pub type TransactionId = (usize, usize);
+
+#[derive(Debug, Default, Clone)]
+pub struct TransactionInfo {
+    /// index of the transaction in its session of the executed history
+    pub index: usize,
+    /// variable -> transaction it was read from (external reads only)
+    pub read: HashMap<usize, TransactionId>,
+    /// every external read in program order, repeated reads of a value only once
+    pub reads: Vec<(usize, TransactionId)>,
+    /// variables written by the transaction
+    pub write: HashSet<usize>,
+    /// invocation and completion of the transaction, if recorded
+    pub time: Option<Timestamps>,
+}
+
+/// Committed transactions of a history together with the write-read relation.
+#[derive(Debug, Default, Clone)]
+pub struct AtomicHistory {
+    pub sessions: Vec<Vec<TransactionInfo>>,
+}
+
+impl AtomicHistory {
+    pub fn txn(&self, id: TransactionId) -> &TransactionInfo {
+        &self.sessions[id.0][id.1]
+    }
+
+    pub fn n_session(&self) -> usize {
+        self.sessions.len()
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = TransactionId> + '_ {
+        self.sessions
+            .iter()
+            .enumerate()
+            .flat_map(|(s, txns)| (0..txns.len()).map(move |i| (s, i)))
+    }
+
+    /// Maps every transaction to a dense index usable with `DiGraph`.
+    pub fn numbering(&self) -> Numbering {
+        let mut offsets = Vec::with_capacity(self.sessions.len());
+        let mut total = 0;
+        for session in self.sessions.iter() {
+            offsets.push(total);
+            total += session.len();
+        }
+        let ids = self.ids().collect();
+        Numbering { offsets, ids }
+    }
+
+    /// variable -> transactions writing it
+    pub fn writers(&self) -> HashMap<usize, Vec<TransactionId>> {
+        let mut writers: HashMap<usize, Vec<TransactionId>> = HashMap::new();
+        for id in self.ids() {
+            for &var in self.txn(id).write.iter() {
+                writers.entry(var).or_default().push(id);
+            }
+        }
+        writers
+    }
+
+    /// (variable, writer) -> transactions reading that write
+    pub fn readers(&self) -> HashMap<(usize, TransactionId), Vec<TransactionId>> {
+        let mut readers: HashMap<(usize, TransactionId), Vec<TransactionId>> = HashMap::new();
+        for id in self.ids() {
+            for (&var, &writer) in self.txn(id).read.iter() {
+                readers.entry((var, writer)).or_default().push(id);
+            }
+        }
+        readers
+    }
+
+    /// Session order and write-read relation as a graph over `numbering()`.
+    pub fn so_wr_graph(&self, numbering: &Numbering) -> DiGraph {
+        let mut graph = DiGraph::new(numbering.len());
+        for (s, session) in self.sessions.iter().enumerate().skip(1) {
+            if !session.is_empty() {
+                graph.add_edge(numbering.index((0, 0)), numbering.index((s, 0)));
+            }
+            for i in 1..session.len() {
+                graph.add_edge(numbering.index((s, i - 1)), numbering.index((s, i)));
+            }
+        }
+        for id in self.ids() {
+            for writer in self.txn(id).read.values() {
+                graph.add_edge(numbering.index(*writer), numbering.index(id));
+            }
+        }
+        graph
+    }
+}
+
+pub struct Numbering {
+    offsets: Vec<usize>,
+    ids: Vec<TransactionId>,
+}
+
+impl Numbering {
+    pub fn index(&self, id: TransactionId) -> usize {
+        self.offsets[id.0] + id.1
+    }
+
+    pub fn id(&self, index: usize) -> TransactionId {
+        self.ids[index]
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BitSet(Vec<u64>);
+
+impl BitSet {
+    pub fn new(size: usize) -> Self {
+        BitSet(vec![0; size.div_ceil(64)])
+    }
+
+    pub fn insert(&mut self, i: usize) {
+        self.0[i / 64] |= 1 << (i % 64);
+    }
+
+    pub fn contains(&self, i: usize) -> bool {
+        self.0[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    pub fn union_with(&mut self, other: &BitSet) {
+        self.0.iter_mut().zip(other.0.iter()).for_each(|(a, b)| *a |= b);
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct DiGraph {
+    adj: Vec<Vec<usize>>,
+}
+
+impl DiGraph {
+    pub fn new(n: usize) -> Self {
+        DiGraph { adj: vec![Vec::new(); n] }
+    }
+
+    pub fn len(&self) -> usize {
+        self.adj.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.adj.is_empty()
+    }
+
+    pub fn add_edge(&mut self, u: usize, v: usize) {
+        self.adj[u].push(v);
+    }
+
+    pub fn successors(&self, u: usize) -> &[usize] {
+        &self.adj[u]
+    }
+
+    /// Kahn's algorithm; `None` if the graph has a cycle.
+    pub fn topological_order(&self) -> Option<Vec<usize>> {
+        let mut in_degree = vec![0; self.len()];
+        self.adj.iter().flatten().for_each(|&v| in_degree[v] += 1);
+
+        let mut stack: Vec<usize> = (0..self.len()).filter(|&u| in_degree[u] == 0).collect();
+        let mut order = Vec::with_capacity(self.len());
+
+        while let Some(u) = stack.pop() {
+            order.push(u);
+            for &v in self.adj[u].iter() {
+                in_degree[v] -= 1;
+                if in_degree[v] == 0 {
+                    stack.push(v);
+                }
+            }
+        }
+
+        if order.len() == self.len() {
+            Some(order)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_acyclic(&self) -> bool {
+        self.topological_order().is_some()
+    }
+
+    /// Strict transitive closure of an acyclic graph: `closure[v]` contains every `u` with a
+    /// non-empty path from `u` to `v`.
+    pub fn transitive_closure(&self, order: &[usize]) -> Vec<BitSet> {
+        let mut closure = vec![BitSet::new(self.len()); self.len()];
+        for &u in order.iter() {
+            let mut reach = closure[u].clone();
+            reach.insert(u);
+            for &v in self.adj[u].iter() {
+                closure[v].union_with(&reach);
+            }
+        }
+        closure
+    }
+}
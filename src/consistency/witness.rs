@@ -0,0 +1,357 @@
+//! Witnesses of consistency violations: cycles of dependencies between committed transactions
+//! which no execution at a level allows.
+//!
+//! Dependencies are session order, write-read relation, and the write-write and read-write
+//! dependencies every execution at the level must have. The latter are inferred the way the
+//! checkers in `algo` order writers, until nothing changes. A violation which is only witnessed
+//! once the order of some writers is guessed has no such cycle.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use super::util::{AtomicHistory, BitSet, DiGraph, Numbering, TransactionId};
+use super::Consistency;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Dependency {
+    /// the transaction is directly before the next one in its session
+    SessionOrder,
+    /// the next transaction reads the variable from the transaction
+    WriteRead(usize),
+    /// the next transaction overwrites the variable written by the transaction
+    WriteWrite(usize),
+    /// the transaction reads the variable before the next transaction overwrites it
+    ReadWrite(usize),
+    /// the transaction completed before the next one was invoked
+    RealTime,
+}
+
+impl Dependency {
+    pub fn variable(&self) -> Option<usize> {
+        match *self {
+            Dependency::WriteRead(var) | Dependency::WriteWrite(var) | Dependency::ReadWrite(var) => Some(var),
+            Dependency::SessionOrder | Dependency::RealTime => None,
+        }
+    }
+
+    fn is_read_write(&self) -> bool {
+        matches!(self, Dependency::ReadWrite(_))
+    }
+}
+
+impl fmt::Display for Dependency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Dependency::SessionOrder => write!(f, "so"),
+            Dependency::WriteRead(var) => write!(f, "wr({})", var),
+            Dependency::WriteWrite(var) => write!(f, "ww({})", var),
+            Dependency::ReadWrite(var) => write!(f, "rw({})", var),
+            Dependency::RealTime => write!(f, "rt"),
+        }
+    }
+}
+
+/// The shortest cycle of dependencies `level` forbids, every transaction with its dependency on
+/// the next one, the last on the first. `None` if there is none without guessing an order of
+/// writers, in particular if the history satisfies `level`.
+pub fn find_cycle(history: &AtomicHistory, level: Consistency) -> Option<Vec<(TransactionId, Dependency)>> {
+    let mut dependencies = Dependencies::new(history, level);
+
+    match level {
+        // session order and write-read relation only
+        Consistency::ReadCommitted => {}
+        Consistency::ReadAtomic => dependencies.read_atomic(),
+        _ => {
+            if level == Consistency::StrictSerializable {
+                dependencies.real_time();
+            }
+            dependencies.saturate();
+        }
+    }
+
+    dependencies.shortest_cycle()
+}
+
+struct Dependencies<'a> {
+    history: &'a AtomicHistory,
+    level: Consistency,
+    numbering: Numbering,
+    writers: HashMap<usize, Vec<TransactionId>>,
+    /// (variable, writer) -> transactions reading that write, including repeated reads
+    readers: HashMap<(usize, TransactionId), Vec<TransactionId>>,
+    edges: Vec<Vec<(usize, Dependency)>>,
+    added: HashSet<(usize, usize, Dependency)>,
+}
+
+impl<'a> Dependencies<'a> {
+    /// Session order, write-read relation, and the initial transaction overwritten by every writer.
+    fn new(history: &'a AtomicHistory, level: Consistency) -> Self {
+        let numbering = history.numbering();
+        let mut readers: HashMap<(usize, TransactionId), Vec<TransactionId>> = HashMap::new();
+        for id in history.ids() {
+            for &(var, writer) in history.txn(id).reads.iter() {
+                readers.entry((var, writer)).or_default().push(id);
+            }
+        }
+
+        let mut dependencies = Dependencies {
+            history,
+            level,
+            edges: vec![Vec::new(); numbering.len()],
+            numbering,
+            writers: history.writers(),
+            readers,
+            added: HashSet::new(),
+        };
+
+        for (s, session) in history.sessions.iter().enumerate().skip(1) {
+            if !session.is_empty() {
+                dependencies.add((0, 0), (s, 0), Dependency::SessionOrder);
+            }
+            for i in 1..session.len() {
+                dependencies.add((s, i - 1), (s, i), Dependency::SessionOrder);
+            }
+        }
+        for id in history.ids() {
+            for &(var, writer) in history.txn(id).reads.iter() {
+                dependencies.add(writer, id, Dependency::WriteRead(var));
+            }
+        }
+        let written: Vec<(usize, TransactionId)> = dependencies
+            .writers
+            .iter()
+            .flat_map(|(&var, writers)| writers.iter().map(move |&w| (var, w)))
+            .filter(|&(_, w)| w != (0, 0))
+            .collect();
+        for (var, writer) in written {
+            dependencies.add((0, 0), writer, Dependency::WriteWrite(var));
+        }
+
+        dependencies
+    }
+
+    /// Whether conflicting transactions must observe each other at the level.
+    fn no_conflict(&self) -> bool {
+        matches!(
+            self.level,
+            Consistency::ParallelSnapshotIsolation | Consistency::SnapshotIsolation
+        )
+    }
+
+    /// Adds a dependency and the ones it implies, returns whether anything was new.
+    fn add(&mut self, from: TransactionId, to: TransactionId, dependency: Dependency) -> bool {
+        let mut changed = false;
+        let mut pending = vec![(from, to, dependency)];
+
+        while let Some((from, to, dependency)) = pending.pop() {
+            let (u, v) = (self.numbering.index(from), self.numbering.index(to));
+            if !self.added.insert((u, v, dependency)) {
+                continue;
+            }
+            self.edges[u].push((v, dependency));
+            changed = true;
+
+            match dependency {
+                // the readers of the overwritten value read before the overwrite
+                Dependency::WriteWrite(var) => {
+                    for &reader in self.readers.get(&(var, from)).into_iter().flatten() {
+                        if reader != to {
+                            pending.push((reader, to, Dependency::ReadWrite(var)));
+                        }
+                    }
+                }
+                // a reader overwriting the value it read does not observe the overwrite, so it
+                // must be observed by it
+                Dependency::ReadWrite(var) if self.no_conflict() && self.history.txn(from).write.contains(&var) => {
+                    pending.push((from, to, Dependency::WriteWrite(var)));
+                }
+                _ => {}
+            }
+        }
+
+        changed
+    }
+
+    /// The orderings of `check_read_atomic`.
+    fn read_atomic(&mut self) {
+        let history = self.history;
+        for (s, session) in history.sessions.iter().enumerate().skip(1) {
+            let mut last_write: HashMap<usize, TransactionId> = HashMap::new();
+            for i in 0..session.len() {
+                let t3 = (s, i);
+                let txn = history.txn(t3);
+                for (&var, &t1) in txn.read.iter() {
+                    let so = last_write.get(&var).copied();
+                    let wr = txn.read.values().copied().filter(|&t2| history.txn(t2).write.contains(&var));
+                    for t2 in so.into_iter().chain(wr) {
+                        if t2 != t1 {
+                            self.add(t2, t1, Dependency::WriteWrite(var));
+                        }
+                    }
+                }
+                for &var in txn.write.iter() {
+                    last_write.insert(var, t3);
+                }
+            }
+        }
+    }
+
+    /// Orders every transaction after the transactions which completed before its invocation; the
+    /// first of every session is enough, session order reaches the rest.
+    fn real_time(&mut self) {
+        let history = self.history;
+        let timed: Vec<(TransactionId, u64)> = history
+            .ids()
+            .filter_map(|id| history.txn(id).time.map(|time| (id, time.completed)))
+            .collect();
+
+        for (before, completed) in timed {
+            for (s, session) in history.sessions.iter().enumerate().skip(1) {
+                let after = session
+                    .iter()
+                    .position(|txn| txn.time.is_some_and(|time| time.invoked > completed));
+                if let Some(i) = after {
+                    self.add(before, (s, i), Dependency::RealTime);
+                }
+            }
+        }
+    }
+
+    /// Whether a path of the level through `dependency` makes the start visible to the end.
+    fn on_path(&self, dependency: Dependency) -> bool {
+        match (self.level, dependency) {
+            (_, Dependency::SessionOrder) | (_, Dependency::WriteRead(_)) => true,
+            (Consistency::Causal, _) => false,
+            (_, Dependency::WriteWrite(_)) => true,
+            (Consistency::Serializable, Dependency::ReadWrite(_)) => true,
+            (Consistency::StrictSerializable, _) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the last dependency of such a path makes the start visible to the end.
+    fn ends_path(&self, dependency: Dependency) -> bool {
+        match (self.level, dependency) {
+            // a transaction may commit after another without observing it
+            (Consistency::Prefix, Dependency::WriteWrite(_)) => false,
+            _ => self.on_path(dependency),
+        }
+    }
+
+    /// Whether a cycle may contain `dependency`.
+    fn on_cycle(&self, dependency: Dependency) -> bool {
+        match (self.level, dependency) {
+            (_, Dependency::RealTime) => self.level == Consistency::StrictSerializable,
+            (_, Dependency::ReadWrite(_)) => self.level >= Consistency::SnapshotIsolation,
+            _ => true,
+        }
+    }
+
+    /// Transactions visible to every transaction, or `None` if visibility is cyclic. Under
+    /// snapshot isolation a path may also continue after an anti-dependency.
+    fn visibility(&self) -> Option<Vec<BitSet>> {
+        let mut graph = DiGraph::new(self.numbering.len());
+        for (u, edges) in self.edges.iter().enumerate() {
+            for &(v, _) in edges.iter().filter(|&&(_, d)| self.on_path(d)) {
+                graph.add_edge(u, v);
+                if self.level == Consistency::SnapshotIsolation {
+                    for &(w, _) in self.edges[v].iter().filter(|(_, d)| d.is_read_write()) {
+                        graph.add_edge(u, w);
+                    }
+                }
+            }
+        }
+
+        let order = graph.topological_order()?;
+        let closure = graph.transitive_closure(&order);
+
+        let mut visible = vec![BitSet::new(self.numbering.len()); self.numbering.len()];
+        for (u, edges) in self.edges.iter().enumerate() {
+            for &(v, _) in edges.iter().filter(|&&(_, d)| self.ends_path(d)) {
+                let mut reach = closure[u].clone();
+                reach.insert(u);
+                visible[v].union_with(&reach);
+            }
+        }
+        Some(visible)
+    }
+
+    /// Whenever `t1` writes `x` and is visible to `t3`, which reads `x` from `t2`, then `t2`
+    /// overwrites `t1`. Repeated until nothing changes or visibility is cyclic.
+    fn saturate(&mut self) {
+        let history = self.history;
+        while let Some(visible) = self.visibility() {
+            let mut changed = false;
+            for t3 in history.ids() {
+                let i3 = self.numbering.index(t3);
+                for (&var, &t2) in history.txn(t3).read.iter() {
+                    for &t1 in self.writers[&var].clone().iter() {
+                        if t1 != t2 && t1 != t3 && visible[i3].contains(self.numbering.index(t1)) {
+                            changed |= self.add(t1, t2, Dependency::WriteWrite(var));
+                        }
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    /// Breadth first search from every transaction; snapshot isolation forbids only cycles
+    /// without two anti-dependencies in a row.
+    fn shortest_cycle(&self) -> Option<Vec<(TransactionId, Dependency)>> {
+        let n = self.numbering.len();
+        let alternating = self.level == Consistency::SnapshotIsolation;
+        let mut best: Option<Vec<(usize, Dependency)>> = None;
+
+        for start in 0..n {
+            // states are (transaction, whether it was reached by an anti-dependency); starting
+            // as if it was forces the first dependency to be another one
+            let state = |u: usize, rw: bool| 2 * u + rw as usize;
+            let mut parent: Vec<Option<(usize, Dependency)>> = vec![None; 2 * n];
+            let mut depth = vec![usize::MAX; 2 * n];
+            let initial = state(start, alternating);
+            depth[initial] = 0;
+            let mut queue = VecDeque::from(vec![initial]);
+            let mut closing = None;
+
+            while let Some(s) = queue.pop_front() {
+                if best.as_ref().is_some_and(|b| depth[s] + 1 >= b.len()) {
+                    break;
+                }
+                let (u, rw) = (s / 2, s % 2 == 1);
+                for &(v, dependency) in self.edges[u].iter().filter(|&&(_, d)| self.on_cycle(d)) {
+                    if alternating && rw && dependency.is_read_write() {
+                        continue;
+                    }
+                    if v == start {
+                        closing = Some((s, dependency));
+                        break;
+                    }
+                    let next = state(v, alternating && dependency.is_read_write());
+                    if depth[next] == usize::MAX {
+                        depth[next] = depth[s] + 1;
+                        parent[next] = Some((s, dependency));
+                        queue.push_back(next);
+                    }
+                }
+                if closing.is_some() {
+                    break;
+                }
+            }
+
+            if let Some((mut s, dependency)) = closing {
+                let mut cycle = vec![(s / 2, dependency)];
+                while let Some((prev, dependency)) = parent[s] {
+                    cycle.push((prev / 2, dependency));
+                    s = prev;
+                }
+                cycle.reverse();
+                best = Some(cycle);
+            }
+        }
+
+        best.map(|cycle| cycle.into_iter().map(|(u, d)| (self.numbering.id(u), d)).collect())
+    }
+}
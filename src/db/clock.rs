@@ -0,0 +1,21 @@
+//! Monotonic time of an execution. Timestamps count from the moment the clock is started, which
+//! is recorded as the wall clock `start` of the executed history; together they place every
+//! transaction in wall clock time without being affected by clock adjustments.
+
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Clock {
+    anchor: Instant,
+}
+
+impl Clock {
+    pub fn start() -> Self {
+        Clock { anchor: Instant::now() }
+    }
+
+    /// Nanoseconds since the clock was started.
+    pub fn now(&self) -> u64 {
+        self.anchor.elapsed().as_nanos() as u64
+    }
+}
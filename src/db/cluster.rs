@@ -1,17 +1,27 @@
+use super::clock::Clock;
+use super::error::DbcopError;
+use super::format::{dump_history, load_history, HistoryFileError};
 use super::history::{HistParams, History, Session};
+use super::manifest::{Manifest, Status};
+use super::retry::RetryPolicy;
 // use verifier::Verifier;
 
 // use std::collections::HashMap;
 
+use std::fmt;
 use std::fs;
-use std::fs::File;
-use std::io::{BufReader, BufWriter};
-use std::path::Path;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 use std::net::SocketAddr;
 
+use clap::ValueEnum;
+
 // use rand::distributions::{Distribution, Uniform};
-// use rand::Rng;
+use rand::Rng;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
 use std::thread;
 use std::thread::sleep;
 use std::time::Duration;
@@ -26,8 +36,125 @@ pub struct Node {
     pub id: usize,
 }
 
+/// Which node every session of a history is executed on. The number of sessions is independent
+/// of the number of nodes; several sessions may share a node.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Placement {
+    /// session `i` runs on node `i % n_node`
+    #[default]
+    RoundRobin,
+    /// session `i` runs on node `nodes[i % nodes.len()]`
+    Pinned(Vec<usize>),
+    /// every session runs on a uniformly chosen node, seeded by the seed of the history
+    Random,
+}
+
+impl Placement {
+    pub fn assign(&self, n_session: usize, n_node: usize, seed: u64) -> Vec<usize> {
+        match self {
+            Placement::RoundRobin => (0..n_session).map(|i| i % n_node).collect(),
+            Placement::Pinned(nodes) => (0..n_session).map(|i| nodes[i % nodes.len()]).collect(),
+            Placement::Random => {
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                (0..n_session).map(|_| rng.gen_range(0..n_node)).collect()
+            }
+        }
+    }
+
+    /// Checks that `assign` can place sessions on a cluster of `n_node` nodes.
+    pub fn validate(&self, n_node: usize) -> Result<(), String> {
+        if n_node == 0 {
+            return Err("the cluster has no nodes".to_string());
+        }
+        match self {
+            Placement::Pinned(nodes) if nodes.is_empty() => Err("pinned placement names no nodes".to_string()),
+            Placement::Pinned(nodes) => match nodes.iter().find(|&&node| node >= n_node) {
+                Some(node) => Err(format!("placement uses node {} but the cluster has only {} nodes", node, n_node)),
+                None => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for Placement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Placement::RoundRobin => write!(f, "round-robin"),
+            Placement::Random => write!(f, "random"),
+            Placement::Pinned(nodes) => {
+                let nodes: Vec<String> = nodes.iter().map(|n| n.to_string()).collect();
+                write!(f, "pinned:{}", nodes.join(","))
+            }
+        }
+    }
+}
+
+/// Parses `round-robin`, `random` or `pinned:<node>,<node>,...`.
+impl FromStr for Placement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "round-robin" => Ok(Placement::RoundRobin),
+            "random" => Ok(Placement::Random),
+            _ => {
+                let nodes = s
+                    .strip_prefix("pinned:")
+                    .ok_or_else(|| format!("unknown placement `{}`, expected round-robin, random or pinned:<nodes>", s))?;
+                let nodes: Vec<usize> = nodes
+                    .split(',')
+                    .map(|n| n.trim().parse())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| format!("expected comma separated node indices, found `{}`", nodes))?;
+                Ok(Placement::Pinned(nodes))
+            }
+        }
+    }
+}
+
+/// Isolation level transactions are executed at, for databases which offer a choice.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Isolation {
+    ReadCommitted,
+    RepeatableRead,
+    #[default]
+    Serializable,
+}
+
+impl fmt::Display for Isolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Isolation::ReadCommitted => write!(f, "read-committed"),
+            Isolation::RepeatableRead => write!(f, "repeatable-read"),
+            Isolation::Serializable => write!(f, "serializable"),
+        }
+    }
+}
+
+/// How the variables of a history come into existence.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Init {
+    /// a variable is inserted by its first write, reading a missing variable returns `0`
+    Lazy,
+    /// every variable is inserted with `0` before the history is executed
+    #[default]
+    Eager,
+}
+
+impl fmt::Display for Init {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Init::Lazy => write!(f, "lazy"),
+            Init::Eager => write!(f, "eager"),
+        }
+    }
+}
+
 pub trait ClusterNode {
-    fn exec_session(&self, hist: &mut Session);
+    /// Executes the transactions of `hist` in order, retrying each as `retry` allows and
+    /// timestamping each with `clock`.
+    fn exec_session(&self, hist: &mut Session, retry: &RetryPolicy, clock: &Clock) -> Result<(), DbcopError>;
 }
 
 pub trait Cluster<N>
@@ -35,11 +162,11 @@ where
     N: 'static + Send + ClusterNode,
 {
     fn n_node(&self) -> usize;
-    fn setup(&self) -> bool;
-    fn setup_test(&mut self, p: &HistParams);
+    fn setup(&self) -> Result<(), DbcopError>;
+    fn setup_test(&mut self, p: &HistParams) -> Result<(), DbcopError>;
     fn get_node(&self, id: usize) -> Node;
     fn get_cluster_node(&self, id: usize) -> N;
-    fn cleanup(&self);
+    fn cleanup(&self) -> Result<(), DbcopError>;
     fn info(&self) -> String;
 
     fn node_vec(ips: &[&str]) -> Vec<Node> where Self: Sized {
@@ -52,18 +179,59 @@ where
             .collect()
     }
 
-    fn execute_all(&mut self, r_dir: &Path, o_dir: &Path, millisec: u64) -> Option<usize> {
-        let histories: Vec<History> = fs::read_dir(r_dir)
-            .unwrap()
-            .filter_map(|entry_res| match entry_res {
-                Ok(ref entry) if !&entry.path().is_dir() => {
-                    let file = File::open(entry.path()).unwrap();
-                    let buf_reader = BufReader::new(file);
-                    Some(bincode::deserialize_from(buf_reader).unwrap())
-                }
-                _ => None,
-            })
+    /// Executes every history file of `r_dir` in turn into the directory of `o_dir` named after
+    /// the file without its extension, recording progress in the manifest of `o_dir`. With
+    /// `resume`, histories completed by a previous run are skipped; otherwise `o_dir` must not
+    /// hold a previous run. A history that fails is recorded as failed and the next one is
+    /// executed.
+    fn execute_all(
+        &mut self,
+        r_dir: &Path,
+        o_dir: &Path,
+        millisec: u64,
+        placement: &Placement,
+        retry: &RetryPolicy,
+        resume: bool,
+    ) -> Result<Manifest, DbcopError> {
+        let mut manifest = match Manifest::load(o_dir)? {
+            Some(manifest) if resume => manifest,
+            Some(_) => {
+                return Err(HistoryFileError::Io(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{:?} holds a previous run, resume it or choose another directory", o_dir),
+                ))
+                .into())
+            }
+            None => Manifest::default(),
+        };
+
+        let mut sources: Vec<PathBuf> = fs::read_dir(r_dir)
+            .map_err(HistoryFileError::from)?
+            .filter_map(|entry_res| entry_res.ok())
+            .map(|entry| entry.path())
+            .filter(|path| !path.is_dir())
             .collect();
+        sources.sort();
+
+        let name_of = |path: &Path| path.file_name().unwrap().to_string_lossy().into_owned();
+        let names: Vec<String> = sources.iter().map(|source| name_of(source)).collect();
+
+        // every history is executed into a directory named after its source file, as histories
+        // of different files may share an id
+        let outputs: Vec<PathBuf> = sources.iter().map(|source| PathBuf::from(source.file_stem().unwrap())).collect();
+        if let Some(i) = (1..outputs.len()).find(|&i| outputs[..i].contains(&outputs[i])) {
+            return Err(HistoryFileError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{:?} and another file of {:?} would both be executed into {:?}", sources[i], r_dir, outputs[i]),
+            ))
+            .into());
+        }
+
+        manifest.retain(&names);
+        for name in names.iter() {
+            manifest.add(name);
+        }
+        manifest.save(o_dir)?;
 
         // let histories: Vec<History> = (0..1000)
         //     .flat_map(|id| {
@@ -75,33 +243,65 @@ where
         //     })
         //     .collect();
 
-        for history in histories.iter() {
-            let curr_dir = o_dir.join(format!("hist-{:05}", history.get_id()));
-            if fs::create_dir(&curr_dir).is_ok() {
-                self.execute(history, &curr_dir);
-                sleep(Duration::from_millis(millisec));
-            } else {
-                println!("skipping {:?}", curr_dir)
+        for ((source, name), output) in sources.iter().zip(names.iter()).zip(outputs) {
+            if manifest.is_complete(name, o_dir) {
+                println!("skipping {:?}, already done", source);
+                continue;
             }
+
+            manifest.set(name, Status::Running, None, None);
+            manifest.save(o_dir)?;
+
+            let result = load_history(source).map_err(DbcopError::from).and_then(|history| {
+                fs::create_dir_all(o_dir.join(&output)).map_err(HistoryFileError::from)?;
+                self.execute(&history, &o_dir.join(&output), placement, retry)?;
+                Ok(output)
+            });
+
+            match result {
+                Ok(output) => manifest.set(name, Status::Done, Some(output), None),
+                Err(e) => {
+                    println!("failed {:?}: {}", source, e);
+                    manifest.set(name, Status::Failed, None, Some(e.to_string()));
+                }
+            }
+            manifest.save(o_dir)?;
+
+            sleep(Duration::from_millis(millisec));
         }
 
-        None
+        Ok(manifest)
     }
 
-    fn execute(&mut self, hist: &History, dir: &Path) -> Option<usize> {
-        self.setup();
+    fn execute(
+        &mut self,
+        hist: &History,
+        dir: &Path,
+        placement: &Placement,
+        retry: &RetryPolicy,
+    ) -> Result<(), DbcopError> {
+        self.setup()?;
 
-        self.setup_test(hist.get_params());
+        self.setup_test(hist.get_params())?;
 
         let mut exec = hist.get_cloned_data();
+        let nodes = placement.assign(exec.len(), self.n_node(), hist.get_params().get_seed());
+        let retry = RetryPolicy {
+            seed: retry.seed.or(Some(hist.get_params().get_seed())),
+            ..*retry
+        };
 
         let start_time = chrono::Local::now();
+        let clock = Clock::start();
 
-        self.exec_history(&mut exec);
+        let result = self.exec_history(&mut exec, &nodes, &retry, &clock);
 
         let end_time = chrono::Local::now();
 
-        self.cleanup();
+        // clean up even if the execution failed, but report the first error
+        let cleanup = self.cleanup();
+        result?;
+        cleanup?;
 
         let exec_hist = History::new(
             hist.get_cloned_params(),
@@ -111,26 +311,52 @@ where
             exec,
         );
 
-        let file = File::create(dir.join("history.bincode")).unwrap();
-        let buf_writer = BufWriter::new(file);
-        bincode::serialize_into(buf_writer, &exec_hist).expect("dumping to bincode went wrong");
-
-        None
+        dump_history(&dir.join("history.bincode"), &exec_hist)?;
+        Ok(())
     }
 
-    fn exec_history(&self, hist: &mut Vec<Session>) {
-        let mut threads = (0..self.n_node())
-            .cycle()
+    /// Runs every session on its own thread, session `i` on node `nodes[i]`. Fails with the error
+    /// of the first session that failed.
+    fn exec_history(
+        &self,
+        hist: &mut Vec<Session>,
+        nodes: &[usize],
+        retry: &RetryPolicy,
+        clock: &Clock,
+    ) -> Result<(), DbcopError> {
+        let mut threads = nodes
+            .iter()
+            .copied()
             .zip(hist.drain(..))
             .map(|(node_id, mut single_hist)| {
                 // println!("Executing on node {}: {:?}", node_id, single_hist);
                 let cluster_node = self.get_cluster_node(node_id);
+                let (retry, clock) = (*retry, *clock);
                 thread::spawn(move || {
-                    cluster_node.exec_session(&mut single_hist);
-                    single_hist
+                    let result = cluster_node.exec_session(&mut single_hist, &retry, &clock);
+                    (single_hist, result)
                 })
             })
             .collect::<Vec<_>>();
-        hist.extend(threads.drain(..).map(|t| t.join().unwrap()));
+
+        let mut error = None;
+        for (i_session, t) in threads.drain(..).enumerate() {
+            match t.join() {
+                Ok((single_hist, result)) => {
+                    hist.push(single_hist);
+                    if let Err(e) = result {
+                        error.get_or_insert(e);
+                    }
+                }
+                Err(_) => {
+                    error.get_or_insert(DbcopError::Transaction(format!("session {} panicked", i_session)));
+                }
+            }
+        }
+
+        match error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
     }
 }
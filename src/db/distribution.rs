@@ -1,17 +1,24 @@
 extern crate rand;
 
-use rand::prelude::{Distribution, ThreadRng};
+use rand::distributions::{Bernoulli, Uniform};
+use rand::prelude::{Distribution, RngCore};
+
+use clap::ValueEnum;
+
+use serde::{Deserialize, Serialize};
+
+use zipf::ZipfDistribution;
 
 pub struct MyDistribution<D: Distribution<usize>> {
     d: D,
 }
 
 pub trait MyDistributionTrait {
-    fn sample(&self, rng: &mut ThreadRng) -> usize;
+    fn sample(&self, rng: &mut dyn RngCore) -> usize;
 }
 
 impl<D: Distribution<usize>> MyDistributionTrait for MyDistribution<D> {
-    fn sample(&self, rng: &mut ThreadRng) -> usize {
+    fn sample(&self, rng: &mut dyn RngCore) -> usize {
         self.d.sample(rng)
     }
 }
@@ -21,3 +28,52 @@ impl<D: Distribution<usize>> MyDistribution<D> {
         MyDistribution { d }
     }
 }
+
+pub struct HotspotDistribution {
+    hot_probability: Bernoulli,
+    hot_key: Uniform<usize>,
+    non_hot_key: Uniform<usize>,
+}
+
+impl Distribution<usize> for HotspotDistribution {
+    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> usize {
+        if self.hot_probability.sample(rng) {
+            self.hot_key.sample(rng)
+        } else {
+            self.non_hot_key.sample(rng)
+        }
+    }
+}
+
+impl HotspotDistribution {
+    pub fn new(n_variables: usize) -> HotspotDistribution {
+        let hot_key_max = n_variables / 5;
+        HotspotDistribution {
+            hot_probability: Bernoulli::new(0.8).unwrap(),
+            hot_key: Uniform::new(0, hot_key_max),
+            non_hot_key: Uniform::new(hot_key_max, n_variables),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, ValueEnum)]
+pub enum KeyDistribution {
+    #[default]
+    Uniform, Zipf, Hotspot
+}
+
+impl KeyDistribution {
+    pub fn build(&self, n_variable: usize) -> Box<dyn MyDistributionTrait> {
+        match self {
+            KeyDistribution::Uniform => Box::new(MyDistribution::new(Uniform::new(0, n_variable))),
+            KeyDistribution::Zipf => Box::new(MyDistribution::new(
+                ZipfDistribution::new(n_variable, 0.5)
+                    .unwrap()
+                    .map(|x| x - 1),
+            )),
+            KeyDistribution::Hotspot => {
+                Box::new(MyDistribution::new(HotspotDistribution::new(n_variable)))
+            }
+        }
+    }
+}
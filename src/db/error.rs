@@ -0,0 +1,54 @@
+use std::error::Error;
+use std::fmt;
+
+use super::format::HistoryFileError;
+
+#[derive(Debug)]
+pub enum DbcopError {
+    /// a node could not be reached, or the connection to it was lost
+    Connection { addr: String, msg: String },
+    /// creating or dropping the schema or the variables failed
+    Setup(String),
+    /// a transaction failed in a way retrying does not fix
+    Transaction(String),
+    /// the database answered something dbcop did not expect
+    Protocol(String),
+    History(HistoryFileError),
+}
+
+impl DbcopError {
+    pub fn connection<A: fmt::Display, E: fmt::Display>(addr: A, e: E) -> Self {
+        DbcopError::Connection {
+            addr: addr.to_string(),
+            msg: e.to_string(),
+        }
+    }
+
+    pub fn setup<E: fmt::Display>(e: E) -> Self {
+        DbcopError::Setup(e.to_string())
+    }
+
+    pub fn protocol<E: fmt::Display>(e: E) -> Self {
+        DbcopError::Protocol(e.to_string())
+    }
+}
+
+impl fmt::Display for DbcopError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DbcopError::Connection { addr, msg } => write!(f, "connection to {} failed: {}", addr, msg),
+            DbcopError::Setup(msg) => write!(f, "setup failed: {}", msg),
+            DbcopError::Transaction(msg) => write!(f, "transaction failed: {}", msg),
+            DbcopError::Protocol(msg) => write!(f, "unexpected answer: {}", msg),
+            DbcopError::History(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for DbcopError {}
+
+impl From<HistoryFileError> for DbcopError {
+    fn from(e: HistoryFileError) -> Self {
+        DbcopError::History(e)
+    }
+}
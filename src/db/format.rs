@@ -0,0 +1,537 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+
+use super::history::History;
+use super::text::{read_text, write_text};
+
+/// Every history file starts with `MAGIC` followed by the format version as a little endian
+/// `u32`; the rest is the bincode encoding of `History` for that version. Files written before
+/// the header was introduced are read as version `0`.
+pub const MAGIC: [u8; 8] = *b"DBCOPHST";
+pub const FORMAT_VERSION: u32 = 3;
+
+#[derive(Debug)]
+pub enum HistoryFileError {
+    Io(io::Error),
+    UnknownVersion(u32),
+    Corrupt(bincode::Error),
+    Json(serde_json::Error),
+    Parse { line: usize, msg: String },
+    DuplicateWrite { variable: usize, value: usize },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum HistoryFormat {
+    Bincode,
+    Json,
+    Text,
+}
+
+impl HistoryFormat {
+    /// Guesses the format from the file extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "bincode" => Some(HistoryFormat::Bincode),
+            "json" => Some(HistoryFormat::Json),
+            "txt" | "hist" => Some(HistoryFormat::Text),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for HistoryFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HistoryFileError::Io(e) => write!(f, "io error: {}", e),
+            HistoryFileError::UnknownVersion(v) => write!(
+                f,
+                "unknown history format version {} (this dbcop reads up to version {})",
+                v, FORMAT_VERSION
+            ),
+            HistoryFileError::Corrupt(e) => write!(f, "malformed history: {}", e),
+            HistoryFileError::Json(e) => write!(f, "malformed json history: {}", e),
+            HistoryFileError::Parse { line, msg } => write!(f, "line {}: {}", line, msg),
+            HistoryFileError::DuplicateWrite { variable, value } => write!(
+                f,
+                "value {} is written more than once to variable {}, writes must be unique",
+                value, variable
+            ),
+        }
+    }
+}
+
+impl Error for HistoryFileError {}
+
+impl From<io::Error> for HistoryFileError {
+    fn from(e: io::Error) -> Self {
+        HistoryFileError::Io(e)
+    }
+}
+
+impl From<bincode::Error> for HistoryFileError {
+    fn from(e: bincode::Error) -> Self {
+        HistoryFileError::Corrupt(e)
+    }
+}
+
+impl From<serde_json::Error> for HistoryFileError {
+    fn from(e: serde_json::Error) -> Self {
+        HistoryFileError::Json(e)
+    }
+}
+
+pub fn read_history<R: Read>(mut reader: R) -> Result<History, HistoryFileError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+
+    if bytes.len() < MAGIC.len() + 4 || bytes[..MAGIC.len()] != MAGIC {
+        let legacy: v0::History = bincode::deserialize(&bytes)?;
+        return Ok(legacy.into());
+    }
+
+    let mut version = [0; 4];
+    version.copy_from_slice(&bytes[MAGIC.len()..MAGIC.len() + 4]);
+    let payload = &bytes[MAGIC.len() + 4..];
+
+    match u32::from_le_bytes(version) {
+        1 => Ok(bincode::deserialize::<v1::History>(payload)?.into()),
+        2 => Ok(bincode::deserialize::<v2::History>(payload)?.into()),
+        3 => Ok(bincode::deserialize(payload)?),
+        v => Err(HistoryFileError::UnknownVersion(v)),
+    }
+}
+
+pub fn write_history<W: Write>(mut writer: W, hist: &History) -> Result<(), HistoryFileError> {
+    writer.write_all(&MAGIC)?;
+    writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+    bincode::serialize_into(&mut writer, hist)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn read_history_as<R: Read>(format: HistoryFormat, reader: R) -> Result<History, HistoryFileError> {
+    match format {
+        HistoryFormat::Bincode => read_history(reader),
+        HistoryFormat::Json => Ok(serde_json::from_reader(reader)?),
+        HistoryFormat::Text => read_text(reader),
+    }
+}
+
+pub fn write_history_as<W: Write>(format: HistoryFormat, mut writer: W, hist: &History) -> Result<(), HistoryFileError> {
+    match format {
+        HistoryFormat::Bincode => write_history(writer, hist),
+        HistoryFormat::Json => {
+            serde_json::to_writer_pretty(&mut writer, hist)?;
+            writeln!(writer)?;
+            writer.flush()?;
+            Ok(())
+        }
+        HistoryFormat::Text => write_text(writer, hist),
+    }
+}
+
+/// Writes to a temporary file next to `path` and renames it over `path` once it is synced, so
+/// `path` never holds a partially written file.
+pub fn write_atomically<F>(path: &Path, write: F) -> Result<(), HistoryFileError>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<(), HistoryFileError>,
+{
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let result = File::create(&tmp).map_err(HistoryFileError::from).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        write(&mut writer)?;
+        writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    });
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+pub fn load_history(path: &Path) -> Result<History, HistoryFileError> {
+    read_history(BufReader::new(File::open(path)?))
+}
+
+pub fn dump_history(path: &Path, hist: &History) -> Result<(), HistoryFileError> {
+    write_atomically(path, |writer| write_history(writer, hist))
+}
+
+pub fn load_history_as(format: HistoryFormat, path: &Path) -> Result<History, HistoryFileError> {
+    read_history_as(format, BufReader::new(File::open(path)?))
+}
+
+pub fn dump_history_as(format: HistoryFormat, path: &Path, hist: &History) -> Result<(), HistoryFileError> {
+    write_atomically(path, |writer| write_history_as(format, writer, hist))
+}
+
+/// Headerless layout written by dbcop before format versioning.
+mod v0 {
+    use chrono::{DateTime, Local};
+    use serde::Deserialize;
+
+    use crate::db::history;
+
+    #[derive(Deserialize)]
+    pub struct Event {
+        write: bool,
+        variable: usize,
+        value: usize,
+        success: bool,
+    }
+
+    #[derive(Deserialize)]
+    pub struct Transaction {
+        events: Vec<Event>,
+        success: bool,
+    }
+
+    #[derive(Deserialize)]
+    pub struct HistParams {
+        id: usize,
+        n_node: usize,
+        n_variable: usize,
+        n_transaction: usize,
+        n_event: usize,
+    }
+
+    #[derive(Deserialize)]
+    pub struct History {
+        params: HistParams,
+        info: String,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+        data: Vec<Vec<Transaction>>,
+    }
+
+    impl From<Event> for history::Event {
+        fn from(e: Event) -> Self {
+            history::Event {
+                write: e.write,
+                variable: e.variable,
+                value: e.value,
+                success: e.success,
+            }
+        }
+    }
+
+    impl From<Transaction> for history::Transaction {
+        fn from(t: Transaction) -> Self {
+            history::Transaction {
+                events: t.events.into_iter().map(From::from).collect(),
+                success: t.success,
+                aborts: Vec::new(),
+                time: None,
+            }
+        }
+    }
+
+    impl From<History> for history::History {
+        fn from(h: History) -> Self {
+            // generator configuration was not recorded, so it is left at its defaults
+            let params = history::HistParams::new(
+                h.params.id,
+                h.params.n_node,
+                h.params.n_variable,
+                h.params.n_transaction,
+                h.params.n_event,
+            );
+            let data = h
+                .data
+                .into_iter()
+                .map(|session| session.into_iter().map(From::from).collect())
+                .collect();
+            history::History::new(params, h.info, h.start, h.end, data)
+        }
+    }
+}
+
+/// Version 1, before aborted attempts were recorded. Events are laid out as in version 0.
+mod v1 {
+    use chrono::{DateTime, Local};
+    use serde::Deserialize;
+
+    use crate::db::{distribution, history};
+
+    pub use super::v0::Event;
+
+    #[derive(Deserialize)]
+    pub struct Transaction {
+        events: Vec<Event>,
+        success: bool,
+    }
+
+    #[derive(Deserialize)]
+    pub enum KeyDistribution {
+        Uniform,
+        Zipf,
+        Hotspot,
+    }
+
+    #[derive(Deserialize)]
+    pub struct HistParams {
+        id: usize,
+        n_node: usize,
+        n_variable: usize,
+        n_transaction: usize,
+        n_event: usize,
+        read_probability: f64,
+        key_distribution: KeyDistribution,
+        longtxn_proportion: f64,
+        longtxn_size: f64,
+        random_txn_size: bool,
+        seed: u64,
+    }
+
+    #[derive(Deserialize)]
+    pub struct History {
+        params: HistParams,
+        info: String,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+        data: Vec<Vec<Transaction>>,
+    }
+
+    impl From<Transaction> for history::Transaction {
+        fn from(t: Transaction) -> Self {
+            history::Transaction {
+                events: t.events.into_iter().map(From::from).collect(),
+                success: t.success,
+                aborts: Vec::new(),
+                time: None,
+            }
+        }
+    }
+
+    impl From<KeyDistribution> for distribution::KeyDistribution {
+        fn from(d: KeyDistribution) -> Self {
+            match d {
+                KeyDistribution::Uniform => distribution::KeyDistribution::Uniform,
+                KeyDistribution::Zipf => distribution::KeyDistribution::Zipf,
+                KeyDistribution::Hotspot => distribution::KeyDistribution::Hotspot,
+            }
+        }
+    }
+
+    impl From<HistParams> for history::HistParams {
+        fn from(p: HistParams) -> Self {
+            history::HistParams {
+                id: p.id,
+                n_node: p.n_node,
+                n_variable: p.n_variable,
+                n_transaction: p.n_transaction,
+                n_event: p.n_event,
+                read_probability: p.read_probability,
+                key_distribution: p.key_distribution.into(),
+                longtxn_proportion: p.longtxn_proportion,
+                longtxn_size: p.longtxn_size,
+                random_txn_size: p.random_txn_size,
+                seed: p.seed,
+            }
+        }
+    }
+
+    impl From<History> for history::History {
+        fn from(h: History) -> Self {
+            let data = h
+                .data
+                .into_iter()
+                .map(|session| session.into_iter().map(From::from).collect())
+                .collect();
+            history::History::new(h.params.into(), h.info, h.start, h.end, data)
+        }
+    }
+}
+
+/// Version 2, before transactions were timestamped. Events and parameters are laid out as in
+/// version 1.
+mod v2 {
+    use chrono::{DateTime, Local};
+    use serde::Deserialize;
+
+    use crate::db::history;
+
+    use super::v1::{Event, HistParams};
+
+    #[derive(Deserialize)]
+    pub enum AbortReason {
+        Serialization,
+        Deadlock,
+        Timeout,
+        ConnectionLost,
+        Other,
+    }
+
+    #[derive(Deserialize)]
+    pub struct Transaction {
+        events: Vec<Event>,
+        success: bool,
+        aborts: Vec<AbortReason>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct History {
+        params: HistParams,
+        info: String,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+        data: Vec<Vec<Transaction>>,
+    }
+
+    impl From<AbortReason> for history::AbortReason {
+        fn from(r: AbortReason) -> Self {
+            match r {
+                AbortReason::Serialization => history::AbortReason::Serialization,
+                AbortReason::Deadlock => history::AbortReason::Deadlock,
+                AbortReason::Timeout => history::AbortReason::Timeout,
+                AbortReason::ConnectionLost => history::AbortReason::ConnectionLost,
+                AbortReason::Other => history::AbortReason::Other,
+            }
+        }
+    }
+
+    impl From<Transaction> for history::Transaction {
+        fn from(t: Transaction) -> Self {
+            history::Transaction {
+                events: t.events.into_iter().map(From::from).collect(),
+                success: t.success,
+                aborts: t.aborts.into_iter().map(From::from).collect(),
+                time: None,
+            }
+        }
+    }
+
+    impl From<History> for history::History {
+        fn from(h: History) -> Self {
+            let data = h
+                .data
+                .into_iter()
+                .map(|session| session.into_iter().map(From::from).collect())
+                .collect();
+            history::History::new(h.params.into(), h.info, h.start, h.end, data)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::history::{AbortReason, Event, Session, Transaction};
+
+    fn event(write: bool, variable: usize, value: usize, success: bool) -> Event {
+        Event {
+            write,
+            variable,
+            value,
+            success,
+        }
+    }
+
+    fn transaction(events: Vec<Event>, success: bool, aborts: Vec<AbortReason>) -> Transaction {
+        Transaction {
+            events,
+            success,
+            aborts,
+            time: None,
+        }
+    }
+
+    /// The sessions of the fixtures, with the abort reasons only versions from 2 on record.
+    fn fixture_data(aborts: bool) -> Vec<Session> {
+        let aborts = |reasons: Vec<AbortReason>| if aborts { reasons } else { Vec::new() };
+        vec![
+            vec![
+                transaction(vec![event(true, 0, 1, true), event(true, 1, 1, true)], true, Vec::new()),
+                transaction(
+                    vec![event(false, 0, 1, true), event(true, 0, 2, true)],
+                    true,
+                    aborts(vec![AbortReason::Serialization]),
+                ),
+            ],
+            vec![
+                transaction(
+                    vec![event(false, 0, 1, true), event(true, 1, 2, false)],
+                    false,
+                    aborts(vec![AbortReason::Timeout, AbortReason::Deadlock]),
+                ),
+                transaction(vec![event(false, 1, 1, true)], true, Vec::new()),
+            ],
+        ]
+    }
+
+    /// Writes `hist` in the current version and checks that it reads back unchanged.
+    fn upgrade(hist: &History) {
+        let mut bytes = Vec::new();
+        write_history(&mut bytes, hist).unwrap();
+        assert_eq!(bytes[..MAGIC.len()], MAGIC);
+        assert_eq!(bytes[MAGIC.len()..MAGIC.len() + 4], FORMAT_VERSION.to_le_bytes());
+
+        let upgraded = read_history(&bytes[..]).unwrap();
+        assert_eq!(upgraded.get_data(), hist.get_data());
+        assert_eq!(upgraded.get_info(), hist.get_info());
+        assert_eq!(upgraded.get_start(), hist.get_start());
+        assert_eq!(upgraded.get_id(), hist.get_id());
+        assert_eq!(upgraded.get_params().get_seed(), hist.get_params().get_seed());
+    }
+
+    #[test]
+    fn reads_version_0() {
+        let hist = read_history(&include_bytes!("fixtures/history-v0.bincode")[..]).unwrap();
+        assert_eq!(hist.get_info(), "PostgreSQL");
+        assert_eq!(hist.get_id(), 3);
+        assert_eq!(hist.get_params().get_n_node(), 2);
+        // not recorded before version 1
+        assert_eq!(hist.get_params().get_seed(), 0);
+        assert_eq!(hist.get_data(), &fixture_data(false));
+        upgrade(&hist);
+    }
+
+    #[test]
+    fn reads_version_1() {
+        let hist = read_history(&include_bytes!("fixtures/history-v1.bincode")[..]).unwrap();
+        assert_eq!(hist.get_id(), 3);
+        assert_eq!(hist.get_params().get_seed(), 42);
+        assert_eq!(hist.get_params().get_read_probability(), 0.5);
+        assert_eq!(hist.get_data(), &fixture_data(false));
+        upgrade(&hist);
+    }
+
+    #[test]
+    fn reads_version_2() {
+        let hist = read_history(&include_bytes!("fixtures/history-v2.bincode")[..]).unwrap();
+        assert_eq!(hist.get_id(), 3);
+        assert_eq!(hist.get_params().get_seed(), 42);
+        assert_eq!(hist.get_data(), &fixture_data(true));
+        upgrade(&hist);
+    }
+
+    #[test]
+    fn rejects_unknown_version() {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            read_history(&bytes[..]),
+            Err(HistoryFileError::UnknownVersion(v)) if v == FORMAT_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn json_round_trips() {
+        let hist = read_history(&include_bytes!("fixtures/history-v2.bincode")[..]).unwrap();
+        let mut json = Vec::new();
+        write_history_as(HistoryFormat::Json, &mut json, &hist).unwrap();
+        let again = read_history_as(HistoryFormat::Json, &json[..]).unwrap();
+        assert_eq!(again.get_data(), hist.get_data());
+        assert_eq!(again.get_params().get_seed(), 42);
+    }
+}
@@ -1,10 +1,14 @@
 use std::fmt;
+use std::str::FromStr;
 
 use std::collections::HashMap;
 
 use rand::distributions::{Distribution, Bernoulli, Uniform};
+use rand::SeedableRng;
 
-use super::distribution::MyDistributionTrait;
+use rand_chacha::ChaCha8Rng;
+
+use super::distribution::KeyDistribution;
 
 use chrono::{DateTime, Duration, Local};
 
@@ -22,12 +26,35 @@ pub struct Event {
 pub struct Transaction {
     pub events: Vec<Event>,
     pub success: bool,
+    /// why every aborted attempt to execute the transaction aborted, in order
+    #[serde(default)]
+    pub aborts: Vec<AbortReason>,
+    /// when the transaction was executed, `None` unless it was executed by `run`
+    #[serde(default)]
+    pub time: Option<Timestamps>,
+}
+
+/// Invocation and completion of a transaction in nanoseconds of a monotonic clock, counted from
+/// the `start` of its history.
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Copy, Debug)]
+pub struct Timestamps {
+    pub invoked: u64,
+    pub completed: u64,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Copy, Debug, Hash)]
+pub enum AbortReason {
+    Serialization,
+    Deadlock,
+    Timeout,
+    ConnectionLost,
+    Other,
 }
 
 pub type Session = Vec<Transaction>;
 
 #[derive(Clone, Copy)]
-pub struct HistoryParams<'a> {
+pub struct HistoryParams {
     pub n_hist: usize,
     pub n_node: usize,
     pub n_variable: usize,
@@ -36,8 +63,9 @@ pub struct HistoryParams<'a> {
     pub read_probability: f64,
     pub longtxn_proportion: f64,
     pub longtxn_size: f64,
-    pub key_distribution: &'a dyn MyDistributionTrait,
+    pub key_distribution: KeyDistribution,
     pub random_txn_size: bool,
+    pub seed: u64,
 }
 
 impl fmt::Debug for Event {
@@ -74,6 +102,46 @@ impl Event {
     }
 }
 
+impl fmt::Display for AbortReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            AbortReason::Serialization => "serialization",
+            AbortReason::Deadlock => "deadlock",
+            AbortReason::Timeout => "timeout",
+            AbortReason::ConnectionLost => "connection-lost",
+            AbortReason::Other => "other",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for AbortReason {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "serialization" => Ok(AbortReason::Serialization),
+            "deadlock" => Ok(AbortReason::Deadlock),
+            "timeout" => Ok(AbortReason::Timeout),
+            "connection-lost" => Ok(AbortReason::ConnectionLost),
+            "other" => Ok(AbortReason::Other),
+            _ => Err(format!("unknown abort reason `{}`", s)),
+        }
+    }
+}
+
+impl Transaction {
+    /// Number of times the transaction was executed, including the one that committed.
+    pub fn attempts(&self) -> usize {
+        self.aborts.len() + self.success as usize
+    }
+
+    /// Nanoseconds from invocation to completion.
+    pub fn latency(&self) -> Option<u64> {
+        self.time.map(|time| time.completed.saturating_sub(time.invoked))
+    }
+}
+
 impl fmt::Debug for Transaction {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let repr = format!("{:?}", self.events);
@@ -84,16 +152,43 @@ impl fmt::Debug for Transaction {
     }
 }
 
+// fields are visible to the crate so older file formats can be migrated field by field
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct HistParams {
-    id: usize,
-    n_node: usize,
-    n_variable: usize,
-    n_transaction: usize,
-    n_event: usize,
+    pub(crate) id: usize,
+    pub(crate) n_node: usize,
+    pub(crate) n_variable: usize,
+    pub(crate) n_transaction: usize,
+    pub(crate) n_event: usize,
+    pub(crate) read_probability: f64,
+    pub(crate) key_distribution: KeyDistribution,
+    pub(crate) longtxn_proportion: f64,
+    pub(crate) longtxn_size: f64,
+    pub(crate) random_txn_size: bool,
+    pub(crate) seed: u64,
 }
 
 impl HistParams {
+    pub fn new(id: usize, n_node: usize, n_variable: usize, n_transaction: usize, n_event: usize) -> Self {
+        HistParams {
+            id,
+            n_node,
+            n_variable,
+            n_transaction,
+            n_event,
+            ..Default::default()
+        }
+    }
+    /// Parameters describing the shape of sessions which were not generated by dbcop.
+    pub fn from_sessions(data: &[Session]) -> Self {
+        HistParams::new(
+            0,
+            data.len(),
+            data.iter().flatten().flat_map(|t| t.events.iter()).map(|e| e.variable + 1).max().unwrap_or(0),
+            data.iter().map(|s| s.len()).max().unwrap_or(0),
+            data.iter().flatten().map(|t| t.events.len()).max().unwrap_or(0),
+        )
+    }
     pub fn get_id(&self) -> usize {
         self.id
     }
@@ -112,6 +207,24 @@ impl HistParams {
     pub fn get_event(&self) -> usize {
         self.n_event
     }
+    pub fn get_read_probability(&self) -> f64 {
+        self.read_probability
+    }
+    pub fn get_key_distribution(&self) -> KeyDistribution {
+        self.key_distribution
+    }
+    pub fn get_longtxn_proportion(&self) -> f64 {
+        self.longtxn_proportion
+    }
+    pub fn get_longtxn_size(&self) -> f64 {
+        self.longtxn_size
+    }
+    pub fn get_random_txn_size(&self) -> bool {
+        self.random_txn_size
+    }
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -160,6 +273,18 @@ impl History {
         self.params.clone()
     }
 
+    pub fn get_info(&self) -> &str {
+        &self.info
+    }
+
+    pub fn get_start(&self) -> DateTime<Local> {
+        self.start
+    }
+
+    pub fn get_end(&self) -> DateTime<Local> {
+        self.end
+    }
+
     pub fn get_duration(&self) -> Duration {
         self.end - self.start
     }
@@ -169,7 +294,8 @@ pub fn generate_single_history(
     params: HistoryParams
 ) -> Vec<Session> {
     let mut counters = HashMap::new();
-    let mut random_generator = rand::thread_rng();
+    let mut random_generator = ChaCha8Rng::seed_from_u64(params.seed);
+    let key_distribution = params.key_distribution.build(params.n_variable);
     let read_distribution = Bernoulli::new(params.read_probability).unwrap();
     let longtxn_distribution = Bernoulli::new(params.longtxn_proportion).unwrap();
     let shorttxn_size_distribution = Uniform::new(1, 2 * params.n_event - 1);
@@ -197,10 +323,10 @@ pub fn generate_single_history(
 
             let generate_event = |_| {
                 if read_distribution.sample(&mut random_generator) {
-                    let variable = params.key_distribution.sample(&mut random_generator);
+                    let variable = key_distribution.sample(&mut random_generator);
                     Event::read(variable)
                 } else {
-                    let variable = params.key_distribution.sample(&mut random_generator);
+                    let variable = key_distribution.sample(&mut random_generator);
                     // let variable = write_variable_range.sample(&mut random_generator);
                     let value = {
                         let entry = counters.entry(variable).or_insert(0);
@@ -214,18 +340,22 @@ pub fn generate_single_history(
             Transaction {
                 events: (0..size).map(generate_event).collect(),
                 success: false,
+                aborts: Vec::new(),
+                time: None,
             }
         }).collect()
     }).collect()
 }
 
+// history `i` is generated from `seed + i`, so it can be regenerated alone from its `HistParams`
 pub fn generate_mult_histories(
     params: HistoryParams
 ) -> Vec<History> {
     (0..params.n_hist).map(|i_hist| -> History {
+        let seed = params.seed.wrapping_add(i_hist as u64);
         let start_time = Local::now();
         let hist = generate_single_history(
-            params
+            HistoryParams { seed, ..params }
         );
         let end_time = Local::now();
         History {
@@ -235,6 +365,12 @@ pub fn generate_mult_histories(
                 n_variable: params.n_variable,
                 n_transaction: params.n_transaction,
                 n_event: params.n_event,
+                read_probability: params.read_probability,
+                key_distribution: params.key_distribution,
+                longtxn_proportion: params.longtxn_proportion,
+                longtxn_size: params.longtxn_size,
+                random_txn_size: params.random_txn_size,
+                seed,
             },
             info: "generated".to_string(),
             start: start_time,
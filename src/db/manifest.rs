@@ -0,0 +1,92 @@
+//! Progress of `run`, kept as `manifest.json` in the output directory so an interrupted run can be
+//! resumed. Histories are keyed by the file name they were read from.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::format::{write_atomically, HistoryFileError};
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Pending,
+    Running,
+    Done,
+    Failed,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub status: Status,
+    /// directory of the executed history, relative to the output directory
+    pub output: Option<PathBuf>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    histories: BTreeMap<String, Entry>,
+}
+
+impl Manifest {
+    pub fn load(o_dir: &Path) -> Result<Option<Self>, HistoryFileError> {
+        let path = o_dir.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_reader(BufReader::new(File::open(path)?))?))
+    }
+
+    pub fn save(&self, o_dir: &Path) -> Result<(), HistoryFileError> {
+        write_atomically(&o_dir.join(MANIFEST_FILE), |writer| {
+            serde_json::to_writer_pretty(&mut *writer, self)?;
+            writeln!(writer)?;
+            Ok(())
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Entry> {
+        self.histories.get(name)
+    }
+
+    pub fn add(&mut self, name: &str) {
+        self.histories.entry(name.to_string()).or_insert(Entry {
+            status: Status::Pending,
+            output: None,
+            error: None,
+        });
+    }
+
+    /// Forgets histories whose source is gone.
+    pub fn retain(&mut self, names: &[String]) {
+        self.histories.retain(|name, _| names.contains(name));
+    }
+
+    pub fn set(&mut self, name: &str, status: Status, output: Option<PathBuf>, error: Option<String>) {
+        self.histories.insert(name.to_string(), Entry { status, output, error });
+    }
+
+    /// A history is complete if it is done and its output is still there.
+    pub fn is_complete(&self, name: &str, o_dir: &Path) -> bool {
+        match self.get(name) {
+            Some(Entry { status: Status::Done, output: Some(output), .. }) => {
+                o_dir.join(output).join("history.bincode").is_file()
+            }
+            _ => false,
+        }
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&String, &Entry)> {
+        self.histories.iter()
+    }
+
+    pub fn count(&self, status: Status) -> usize {
+        self.histories.values().filter(|e| e.status == status).count()
+    }
+}
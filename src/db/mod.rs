@@ -1,3 +1,9 @@
+pub mod clock;
 pub mod cluster;
+pub mod format;
 pub mod history;
+pub mod manifest;
 pub mod distribution;
+pub mod error;
+pub mod retry;
+pub mod text;
@@ -0,0 +1,139 @@
+//! How often and for how long a transaction is retried before it is given up. Every aborted
+//! attempt is recorded in `Transaction::aborts`, so abort rates can be read off the executed
+//! history.
+
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use super::clock::Clock;
+use super::error::DbcopError;
+use super::history::{AbortReason, Timestamps, Transaction};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// attempts before a transaction is given up and left aborted, `None` retries forever
+    pub max_attempts: Option<usize>,
+    /// enforced by the database, a statement running longer aborts the attempt
+    pub statement_timeout: Option<Duration>,
+    /// checked between statements, an attempt running longer is rolled back
+    pub transaction_timeout: Option<Duration>,
+    /// pause before the first retry, doubled after every further abort, and shortened by up to
+    /// half at random
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+    /// seeds the random part of the backoff, `None` for the seed of the history
+    pub seed: Option<u64>,
+    /// keep every aborted attempt as a transaction of its own, see `RetryPolicy::run`
+    pub record_aborted: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: Some(100),
+            statement_timeout: None,
+            transaction_timeout: None,
+            backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+            seed: None,
+            record_aborted: false,
+        }
+    }
+}
+
+/// Point in time an attempt has to finish by.
+#[derive(Debug, Clone, Copy)]
+pub struct Deadline(Option<Instant>);
+
+impl Deadline {
+    pub fn after(timeout: Option<Duration>) -> Self {
+        Deadline(timeout.map(|timeout| Instant::now() + timeout))
+    }
+
+    pub fn expired(&self) -> bool {
+        self.0.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+impl RetryPolicy {
+    /// Pause after the `n_abort`-th abort of a transaction.
+    pub fn backoff(&self, n_abort: usize) -> Duration {
+        let factor = 1u32
+            .checked_shl(n_abort.saturating_sub(1) as u32)
+            .unwrap_or(u32::MAX);
+        self.backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+
+    /// Random factors of the backoff of `transaction`, drawn from `seed` in a stream chosen by the
+    /// first write of the transaction, so that they don't depend on how the sessions interleave.
+    /// Written values are unique per variable, so every transaction with a write has a stream of
+    /// its own; read-only transactions share stream `0`.
+    fn jitter(&self, transaction: &Transaction) -> ChaCha8Rng {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed.unwrap_or_default());
+        if let Some(event) = transaction.events.iter().find(|event| event.write) {
+            rng.set_stream(((event.variable as u64) << 32) ^ event.value as u64);
+        }
+        rng
+    }
+
+    /// Runs `attempt` until `transaction` commits or the attempts are used up. `attempt` executes
+    /// the transaction once and returns why it aborted, `None` if it committed. Errors of
+    /// `attempt` end the retries and are passed on. The transaction is timestamped from its first
+    /// invocation to the completion of its last attempt.
+    ///
+    /// Returns the aborted attempts before the last one if `record_aborted` is set, otherwise
+    /// nothing. They go before `transaction` in its session. Each recorded attempt is an aborted
+    /// transaction with the values it read and its reason in `aborts`; `transaction` then only
+    /// accounts for its last attempt, so every transaction of the session is one attempt, and
+    /// each is timestamped by its own attempt.
+    pub fn run<F>(
+        &self,
+        transaction: &mut Transaction,
+        clock: &Clock,
+        mut attempt: F,
+    ) -> Result<Vec<Transaction>, DbcopError>
+    where
+        F: FnMut(&mut Transaction, Deadline) -> Result<Option<AbortReason>, DbcopError>,
+    {
+        let mut aborted = Vec::new();
+        let mut jitter = self.jitter(transaction);
+        let mut n_abort = 0;
+        let mut invoked = clock.now();
+        transaction.success = false;
+        loop {
+            transaction.events.iter_mut().for_each(|event| event.success = false);
+
+            let reason = attempt(transaction, Deadline::after(self.transaction_timeout))?;
+            transaction.time = Some(Timestamps {
+                invoked,
+                completed: clock.now(),
+            });
+
+            match reason {
+                None => {
+                    transaction.success = true;
+                    return Ok(aborted);
+                }
+                Some(reason) => {
+                    n_abort += 1;
+                    transaction.aborts.push(reason);
+                    if self.max_attempts.is_some_and(|max| n_abort >= max) {
+                        return Ok(aborted);
+                    }
+                    if self.record_aborted {
+                        aborted.push(transaction.clone());
+                        transaction.aborts.clear();
+                    }
+                    sleep(self.backoff(n_abort).mul_f64(jitter.gen_range(0.5..=1.0)));
+                    if self.record_aborted {
+                        invoked = clock.now();
+                    }
+                }
+            }
+        }
+    }
+}
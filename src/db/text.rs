@@ -0,0 +1,340 @@
+//! Line oriented text rendering of a `History`, e.g.
+//!
+//! ```text
+//! info PostgreSQL
+//! start 2022-08-01T10:00:00+02:00
+//! end 2022-08-01T10:00:05+02:00
+//! param n_node 2
+//! session 0 txn 0: W(3,17) R(4,2) commit
+//! session 1 txn 0: R(3,17) !W(4,1) abort
+//! session 1 txn 1: W(4,2) commit aborted(serialization,timeout) at(1200500,3400100)
+//! ```
+//!
+//! A `!` marks an unsuccessful event; `aborted(...)` lists why earlier attempts of a transaction
+//! aborted and `at(...)` when it was invoked and completed, in nanoseconds since `start`. Line
+//! breaks and backslashes in `info` are escaped as `\n`, `\r` and `\\`. Blank lines and lines
+//! starting with `#` are ignored. Header lines are optional, so regression histories can be
+//! written by hand; parameters not given keep their defaults.
+
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+
+use chrono::{DateTime, Local, TimeZone};
+
+use super::format::HistoryFileError;
+use super::history::{Event, HistParams, History, Session, Timestamps, Transaction};
+
+pub fn render_event(event: &Event) -> String {
+    format!(
+        "{}{}({},{})",
+        if event.success { "" } else { "!" },
+        if event.write { 'W' } else { 'R' },
+        event.variable,
+        event.value
+    )
+}
+
+pub fn render_transaction(transaction: &Transaction) -> String {
+    let mut line = String::new();
+    for event in transaction.events.iter() {
+        write!(line, "{} ", render_event(event)).unwrap();
+    }
+    line.push_str(if transaction.success { "commit" } else { "abort" });
+    if !transaction.aborts.is_empty() {
+        let aborts: Vec<String> = transaction.aborts.iter().map(|r| r.to_string()).collect();
+        write!(line, " aborted({})", aborts.join(",")).unwrap();
+    }
+    if let Some(time) = transaction.time {
+        write!(line, " at({},{})", time.invoked, time.completed).unwrap();
+    }
+    line
+}
+
+fn escape_info(info: &str) -> String {
+    info.replace('\\', "\\\\").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+// a backslash before any other character is kept, as in hand written paths
+fn unescape_info(info: &str) -> String {
+    let mut unescaped = String::with_capacity(info.len());
+    let mut chars = info.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('\\') => unescaped.push('\\'),
+            Some(other) => {
+                unescaped.push('\\');
+                unescaped.push(other);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+pub fn write_text<W: Write>(mut writer: W, hist: &History) -> Result<(), HistoryFileError> {
+    writeln!(writer, "info {}", escape_info(hist.get_info()))?;
+    writeln!(writer, "start {}", hist.get_start().to_rfc3339())?;
+    writeln!(writer, "end {}", hist.get_end().to_rfc3339())?;
+
+    if let serde_json::Value::Object(params) = serde_json::to_value(hist.get_params())? {
+        for (key, value) in params {
+            writeln!(writer, "param {} {}", key, value)?;
+        }
+    }
+
+    for (i_session, session) in hist.get_data().iter().enumerate() {
+        if session.is_empty() {
+            writeln!(writer, "session {}", i_session)?;
+        }
+        for (i_txn, transaction) in session.iter().enumerate() {
+            writeln!(writer, "session {} txn {}: {}", i_session, i_txn, render_transaction(transaction))?;
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+fn parse_event(token: &str) -> Option<Event> {
+    let (success, token) = match token.strip_prefix('!') {
+        Some(rest) => (false, rest),
+        None => (true, token),
+    };
+    let write = match token.chars().next()? {
+        'W' => true,
+        'R' => false,
+        _ => return None,
+    };
+    let (variable, value) = token[1..].strip_prefix('(')?.strip_suffix(')')?.split_once(',')?;
+    Some(Event {
+        write,
+        variable: variable.trim().parse().ok()?,
+        value: value.trim().parse().ok()?,
+        success,
+    })
+}
+
+fn parse_transaction(line: &str) -> Option<Transaction> {
+    let mut tokens: Vec<&str> = line.split_whitespace().collect();
+    let time = match tokens.last()?.strip_prefix("at(") {
+        Some(time) => {
+            tokens.pop();
+            let (invoked, completed) = time.strip_suffix(')')?.split_once(',')?;
+            Some(Timestamps {
+                invoked: invoked.trim().parse().ok()?,
+                completed: completed.trim().parse().ok()?,
+            })
+        }
+        None => None,
+    };
+    let aborts = match tokens.last()?.strip_prefix("aborted(") {
+        Some(reasons) => {
+            tokens.pop();
+            reasons
+                .strip_suffix(')')?
+                .split(',')
+                .map(|reason| reason.trim().parse().ok())
+                .collect::<Option<_>>()?
+        }
+        None => Vec::new(),
+    };
+    let success = match tokens.pop()? {
+        "commit" => true,
+        "abort" => false,
+        _ => return None,
+    };
+    Some(Transaction {
+        events: tokens.into_iter().map(parse_event).collect::<Option<_>>()?,
+        success,
+        aborts,
+        time,
+    })
+}
+
+fn parse_time(value: &str) -> Option<DateTime<Local>> {
+    DateTime::parse_from_rfc3339(value).ok().map(|t| t.with_timezone(&Local))
+}
+
+pub fn read_text<R: Read>(mut reader: R) -> Result<History, HistoryFileError> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+
+    let mut info = String::new();
+    let mut start = None;
+    let mut end = None;
+    let mut params = serde_json::Map::new();
+    let mut data: Vec<Session> = Vec::new();
+
+    for (i_line, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let error = |msg: &str| HistoryFileError::Parse {
+            line: i_line + 1,
+            msg: msg.to_string(),
+        };
+
+        let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+
+        match keyword {
+            "info" => info = unescape_info(rest),
+            "start" => start = Some(parse_time(rest).ok_or_else(|| error("invalid start time"))?),
+            "end" => end = Some(parse_time(rest).ok_or_else(|| error("invalid end time"))?),
+            "param" => {
+                let (key, value) = rest.split_once(char::is_whitespace).ok_or_else(|| error("expected `param <name> <value>`"))?;
+                let value = serde_json::from_str(value.trim()).map_err(|_| error("invalid parameter value"))?;
+                params.insert(key.to_string(), value);
+            }
+            "session" => {
+                let (header, transaction) = match rest.split_once(':') {
+                    Some((header, transaction)) => (header, Some(transaction)),
+                    None => (rest, None),
+                };
+                let numbers: Vec<usize> = header
+                    .split_whitespace()
+                    .filter(|&token| token != "txn")
+                    .map(|token| token.parse())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| error("expected `session <n> txn <m>: ...`"))?;
+
+                let i_session = *numbers.first().ok_or_else(|| error("missing session number"))?;
+                if data.len() <= i_session {
+                    data.resize(i_session + 1, Vec::new());
+                }
+
+                match (numbers.get(1), transaction) {
+                    (None, None) => {}
+                    (Some(&i_txn), Some(transaction)) => {
+                        if i_txn != data[i_session].len() {
+                            return Err(error(&format!(
+                                "expected txn {} of session {}",
+                                data[i_session].len(),
+                                i_session
+                            )));
+                        }
+                        let transaction = parse_transaction(transaction).ok_or_else(|| {
+                            error("expected events like `W(x,v)` or `R(x,v)` followed by `commit` or `abort` and optionally `aborted(reason,...)` and `at(invoked,completed)`")
+                        })?;
+                        data[i_session].push(transaction);
+                    }
+                    _ => return Err(error("expected `session <n> txn <m>: ...`")),
+                }
+            }
+            _ => return Err(error(&format!("unknown keyword `{}`", keyword))),
+        }
+    }
+
+    let mut defaults = HistParams::from_sessions(&data);
+    if !params.is_empty() {
+        if let serde_json::Value::Object(mut merged) = serde_json::to_value(&defaults)? {
+            merged.extend(params);
+            defaults = serde_json::from_value(serde_json::Value::Object(merged))?;
+        }
+    }
+
+    let epoch = Local.timestamp_opt(0, 0).unwrap();
+
+    Ok(History::new(
+        defaults,
+        info,
+        start.unwrap_or(epoch),
+        end.unwrap_or(epoch),
+        data,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::history::AbortReason;
+
+    const SESSIONS: &str = "\
+session 0 txn 0: W(0,1) W(1,1) commit at(100,2500)
+session 0 txn 1: R(0,1) !W(0,2) abort aborted(serialization,timeout) at(2600,9000)
+session 1
+session 2 txn 0: R(0,0) R(1,1) commit
+";
+
+    fn round_trip(text: &str) -> String {
+        let hist = read_text(text.as_bytes()).unwrap();
+        let mut out = Vec::new();
+        write_text(&mut out, &hist).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn text_round_trips() {
+        // rendered in the local time zone, like `write_text` does
+        let start = Local.timestamp_opt(1_659_340_800, 0).unwrap();
+        let end = Local.timestamp_opt(1_659_340_805, 0).unwrap();
+        let text = format!(
+            "info PostgreSQL
+start {}
+end {}
+param id 7
+param key_distribution \"Zipf\"
+param longtxn_proportion 0.1
+param longtxn_size 10.0
+param n_event 2
+param n_node 3
+param n_transaction 2
+param n_variable 2
+param random_txn_size true
+param read_probability 0.5
+param seed 42
+{}",
+            start.to_rfc3339(),
+            end.to_rfc3339(),
+            SESSIONS
+        );
+        assert_eq!(round_trip(&text), text);
+    }
+
+    #[test]
+    fn info_with_line_breaks_round_trips() {
+        let info = "PostgreSQL\nnode 1 down\r\nC:\\dbcop \\n";
+        let hist = History::new(HistParams::default(), info.to_string(), Local::now(), Local::now(), Vec::new());
+        let mut text = Vec::new();
+        write_text(&mut text, &hist).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&text).lines().next(),
+            Some("info PostgreSQL\\nnode 1 down\\r\\nC:\\\\dbcop \\\\n")
+        );
+        assert_eq!(read_text(&text[..]).unwrap().get_info(), info);
+
+        // unknown escapes of hand written histories are kept
+        let hist = read_text("info C:\\tmp\\dbcop\\\n".as_bytes()).unwrap();
+        assert_eq!(hist.get_info(), "C:\\tmp\\dbcop\\");
+    }
+
+    #[test]
+    fn header_is_optional() {
+        let text = format!("# hand written\n\n{}", SESSIONS);
+        let hist = read_text(text.as_bytes()).unwrap();
+        assert_eq!(hist.get_info(), "");
+        assert_eq!(hist.get_params().get_n_node(), 3);
+        assert_eq!(hist.get_data()[0][1].aborts, vec![AbortReason::Serialization, AbortReason::Timeout]);
+        assert!(hist.get_data()[1].is_empty());
+
+        // once written, the header is complete and the history reads back the same
+        assert_eq!(round_trip(&round_trip(&text)), round_trip(&text));
+    }
+
+    #[test]
+    fn reports_the_line_of_a_parse_error() {
+        let text = "session 0 txn 0: W(0,1) commit\nsession 0 txn 2: R(0,1) commit\n";
+        match read_text(text.as_bytes()) {
+            Err(HistoryFileError::Parse { line, .. }) => assert_eq!(line, 2),
+            other => panic!("expected a parse error, got {:?}", other.map(|hist| hist.get_id())),
+        }
+    }
+}
@@ -0,0 +1,237 @@
+//! Cobra client logs: one file `T<session>.log` per session, each a sequence of big endian
+//! records tagged by one byte:
+//!
+//! * `S txn` / `C txn` / `A txn`: start, commit or abort of transaction `txn`
+//! * `W write_id key value`: write of `value` to `key`
+//! * `R txn write_id key value`: read of `value` from `key`, written by `write_id` of `txn`
+//!
+//! Reads of the initial value point to `INIT_TXN`/`INIT_WRITE`.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::Path;
+
+use crate::db::format::HistoryFileError;
+use crate::db::history::{Event, Session, Transaction};
+
+use super::check_unique_writes;
+
+pub const INIT_TXN: u64 = 0xbebeebee;
+pub const INIT_WRITE: u64 = 0xbebeebee;
+
+fn txn_id(session: usize, txn: usize) -> u64 {
+    ((session as u64 + 1) << 32) | txn as u64
+}
+
+pub fn write_cobra(dir: &Path, sessions: &[Session]) -> Result<(), HistoryFileError> {
+    fs::create_dir_all(dir)?;
+
+    // (session, txn, event) -> write id
+    let mut write_ids = HashMap::new();
+    // (variable, value) -> (txn, write id, committed), a retry wins over its aborted attempts
+    let mut writers: HashMap<(usize, usize), (u64, u64, bool)> = HashMap::new();
+    let mut next_write: u64 = 1;
+    for (i_session, session) in sessions.iter().enumerate() {
+        for (i_txn, transaction) in session.iter().enumerate() {
+            for (i_event, event) in transaction.events.iter().enumerate().filter(|(_, e)| e.write) {
+                write_ids.insert((i_session, i_txn, i_event), next_write);
+                let key = (event.variable, event.value);
+                if writers.get(&key).is_none_or(|&(_, _, committed)| !committed) {
+                    writers.insert(key, (txn_id(i_session, i_txn), next_write, transaction.success));
+                }
+                next_write += 1;
+            }
+        }
+    }
+
+    for (i_session, session) in sessions.iter().enumerate() {
+        let mut writer = BufWriter::new(File::create(dir.join(format!("T{}.log", i_session)))?);
+        for (i_txn, transaction) in session.iter().enumerate() {
+            let id = txn_id(i_session, i_txn);
+            writer.write_all(b"S")?;
+            writer.write_all(&id.to_be_bytes())?;
+            for (i_event, event) in transaction.events.iter().enumerate().filter(|(_, e)| e.success) {
+                if event.write {
+                    let write_id = write_ids[&(i_session, i_txn, i_event)];
+                    writer.write_all(b"W")?;
+                    writer.write_all(&write_id.to_be_bytes())?;
+                } else {
+                    let (txn, write_id) = writers
+                        .get(&(event.variable, event.value))
+                        .map(|&(txn, write_id, _)| (txn, write_id))
+                        .unwrap_or((INIT_TXN, INIT_WRITE));
+                    writer.write_all(b"R")?;
+                    writer.write_all(&txn.to_be_bytes())?;
+                    writer.write_all(&write_id.to_be_bytes())?;
+                }
+                writer.write_all(&(event.variable as u64).to_be_bytes())?;
+                writer.write_all(&(event.value as u64).to_be_bytes())?;
+            }
+            writer.write_all(if transaction.success { b"C" } else { b"A" })?;
+            writer.write_all(&id.to_be_bytes())?;
+        }
+        writer.flush()?;
+    }
+
+    Ok(())
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64, HistoryFileError> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_be_bytes(bytes))
+}
+
+enum Op {
+    Write { id: u64, key: u64, value: u64 },
+    Read { writer: (u64, u64), key: u64, value: u64 },
+}
+
+struct CobraTxn {
+    ops: Vec<Op>,
+    committed: bool,
+}
+
+fn read_log<R: Read>(mut reader: R) -> Result<Vec<CobraTxn>, HistoryFileError> {
+    let mut log = Vec::new();
+    let mut current: Option<CobraTxn> = None;
+    let mut record = 0;
+
+    loop {
+        let mut tag = [0; 1];
+        match reader.read_exact(&mut tag) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        }
+        record += 1;
+        let error = |msg: &str| HistoryFileError::Parse {
+            line: record,
+            msg: msg.to_string(),
+        };
+
+        match tag[0] {
+            b'S' => {
+                read_u64(&mut reader)?;
+                if current.is_some() {
+                    return Err(error("transaction started inside a transaction"));
+                }
+                current = Some(CobraTxn { ops: Vec::new(), committed: false });
+            }
+            b'C' | b'A' => {
+                read_u64(&mut reader)?;
+                let mut transaction =
+                    current.take().ok_or_else(|| error("end of transaction outside a transaction"))?;
+                transaction.committed = tag[0] == b'C';
+                log.push(transaction);
+            }
+            b'W' | b'R' => {
+                let op = if tag[0] == b'W' {
+                    Op::Write {
+                        id: read_u64(&mut reader)?,
+                        key: read_u64(&mut reader)?,
+                        value: read_u64(&mut reader)?,
+                    }
+                } else {
+                    Op::Read {
+                        writer: (read_u64(&mut reader)?, read_u64(&mut reader)?),
+                        key: read_u64(&mut reader)?,
+                        value: read_u64(&mut reader)?,
+                    }
+                };
+                current
+                    .as_mut()
+                    .ok_or_else(|| error("operation outside a transaction"))?
+                    .ops
+                    .push(op);
+            }
+            _ => return Err(error("unknown record")),
+        }
+    }
+
+    if current.is_some() {
+        return Err(HistoryFileError::Parse {
+            line: record,
+            msg: "unterminated transaction".to_string(),
+        });
+    }
+
+    Ok(log)
+}
+
+/// Reads the logs of a directory, ordered by session. Logs need not come from `write_cobra`:
+/// keys are renumbered densely in increasing order, and a read takes the value of the write it
+/// names by write id, falling back to its recorded value if that write is not in any log.
+pub fn read_cobra(dir: &Path) -> Result<Vec<Session>, HistoryFileError> {
+    let mut logs: Vec<(usize, std::path::PathBuf)> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let n = name.strip_prefix('T')?.strip_suffix(".log")?.parse().ok()?;
+            Some((n, entry.path()))
+        })
+        .collect();
+    logs.sort();
+
+    let logs = logs
+        .iter()
+        .map(|(_, path)| read_log(BufReader::new(File::open(path)?)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let ops = || logs.iter().flatten().flat_map(|t| t.ops.iter());
+
+    let mut keys: Vec<u64> = ops()
+        .map(|op| match op {
+            Op::Write { key, .. } | Op::Read { key, .. } => *key,
+        })
+        .collect();
+    keys.sort_unstable();
+    keys.dedup();
+    let variable = |key: u64| keys.binary_search(&key).unwrap();
+
+    let written: HashMap<u64, u64> = ops()
+        .filter_map(|op| match op {
+            Op::Write { id, value, .. } => Some((*id, *value)),
+            Op::Read { .. } => None,
+        })
+        .collect();
+
+    let sessions: Vec<Session> = logs
+        .iter()
+        .map(|log| {
+            log.iter()
+                .map(|transaction| Transaction {
+                    events: transaction
+                        .ops
+                        .iter()
+                        .map(|op| match *op {
+                            Op::Write { key, value, .. } => Event {
+                                write: true,
+                                variable: variable(key),
+                                value: value as usize,
+                                success: true,
+                            },
+                            Op::Read { writer, key, value } => Event {
+                                write: false,
+                                variable: variable(key),
+                                value: if writer == (INIT_TXN, INIT_WRITE) {
+                                    0
+                                } else {
+                                    *written.get(&writer.1).unwrap_or(&value) as usize
+                                },
+                                success: true,
+                            },
+                        })
+                        .collect(),
+                    success: transaction.committed,
+                    aborts: Vec::new(),
+                    time: None,
+                })
+                .collect()
+        })
+        .collect();
+
+    check_unique_writes(&sessions)?;
+    Ok(sessions)
+}
@@ -0,0 +1,201 @@
+//! Just enough of EDN to read Jepsen histories. Tagged literals are read as the tagged value.
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Edn {
+    Nil,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Keyword(String),
+    Symbol(String),
+    Vector(Vec<Edn>),
+    Map(Vec<(Edn, Edn)>),
+    Set(Vec<Edn>),
+}
+
+impl Edn {
+    pub fn get(&self, keyword: &str) -> Option<&Edn> {
+        match self {
+            Edn::Map(entries) => entries.iter().find_map(|(k, v)| match k {
+                Edn::Keyword(k) if k == keyword => Some(v),
+                _ => None,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn as_keyword(&self) -> Option<&str> {
+        match self {
+            Edn::Keyword(k) => Some(k),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Edn::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_vec(&self) -> Option<&[Edn]> {
+        match self {
+            Edn::Vector(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+pub struct EdnReader<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl<'a> EdnReader<'a> {
+    pub fn new(text: &'a str) -> Self {
+        EdnReader {
+            chars: text.chars().peekable(),
+            line: 1,
+        }
+    }
+
+    /// Line of the next unread character.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(&c) = self.chars.peek() {
+            if c.is_whitespace() || c == ',' {
+                self.bump();
+            } else if c == ';' {
+                while let Some(c) = self.bump() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn token(&mut self) -> String {
+        let mut token = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if c.is_whitespace() || c == ',' || "()[]{}\";".contains(c) {
+                break;
+            }
+            token.push(c);
+            self.bump();
+        }
+        token
+    }
+
+    fn seq(&mut self, close: char) -> Result<Vec<Edn>, String> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.chars.peek() {
+                Some(&c) if c == close => {
+                    self.bump();
+                    return Ok(items);
+                }
+                Some(_) => items.push(self.read()?.ok_or("unexpected end of input")?),
+                None => return Err(format!("missing `{}`", close)),
+            }
+        }
+    }
+
+    /// Reads the next value, `None` at the end of input.
+    pub fn read(&mut self) -> Result<Option<Edn>, String> {
+        self.skip_whitespace();
+        let c = match self.bump() {
+            Some(c) => c,
+            None => return Ok(None),
+        };
+
+        let value = match c {
+            '[' => Edn::Vector(self.seq(']')?),
+            '(' => Edn::Vector(self.seq(')')?),
+            '{' => {
+                let items = self.seq('}')?;
+                if items.len() % 2 != 0 {
+                    return Err("map with odd number of forms".to_string());
+                }
+                let mut entries = Vec::new();
+                let mut items = items.into_iter();
+                while let (Some(k), Some(v)) = (items.next(), items.next()) {
+                    entries.push((k, v));
+                }
+                Edn::Map(entries)
+            }
+            '#' => match self.chars.peek() {
+                Some('{') => {
+                    self.bump();
+                    Edn::Set(self.seq('}')?)
+                }
+                Some('_') => {
+                    self.bump();
+                    self.read()?;
+                    return self.read();
+                }
+                _ => {
+                    self.token();
+                    return self.read();
+                }
+            },
+            '"' => {
+                let mut s = String::new();
+                loop {
+                    match self.bump() {
+                        Some('"') => break,
+                        Some('\\') => match self.bump() {
+                            Some('n') => s.push('\n'),
+                            Some('t') => s.push('\t'),
+                            Some(c) => s.push(c),
+                            None => return Err("unterminated string".to_string()),
+                        },
+                        Some(c) => s.push(c),
+                        None => return Err("unterminated string".to_string()),
+                    }
+                }
+                Edn::Str(s)
+            }
+            ':' => Edn::Keyword(self.token()),
+            ')' | ']' | '}' => return Err(format!("unexpected `{}`", c)),
+            c => {
+                let token = format!("{}{}", c, self.token());
+                match token.as_str() {
+                    "nil" => Edn::Nil,
+                    "true" => Edn::Bool(true),
+                    "false" => Edn::Bool(false),
+                    _ => {
+                        let number = token.trim_end_matches(['N', 'M']);
+                        if let Ok(i) = number.parse() {
+                            Edn::Int(i)
+                        } else if let Ok(f) = number.parse() {
+                            Edn::Float(f)
+                        } else {
+                            Edn::Symbol(token)
+                        }
+                    }
+                }
+            }
+        };
+
+        Ok(Some(value))
+    }
+}
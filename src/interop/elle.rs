@@ -0,0 +1,256 @@
+//! Jepsen/Elle rw-register histories in EDN. Every session becomes a `:process`, every
+//! transaction an `:invoke` followed by `:ok` or `:fail` with `[:r x v]`/`[:w x v]` micro-ops.
+//! Reads of the initial value `0` are `nil` for Elle. The `:time` of both operations, in
+//! nanoseconds, is the invocation and completion time of the transaction.
+
+use std::collections::{BTreeMap, HashSet};
+use std::io::{self, Read, Write};
+
+use crate::db::format::HistoryFileError;
+use crate::db::history::{Event, Session, Timestamps, Transaction};
+
+use super::check_unique_writes;
+use super::edn::{Edn, EdnReader};
+
+fn micro_op(event: &Event, completed: bool) -> String {
+    if event.write {
+        format!("[:w {} {}]", event.variable, event.value)
+    } else if completed && event.success && event.value != 0 {
+        format!("[:r {} {}]", event.variable, event.value)
+    } else {
+        format!("[:r {} nil]", event.variable)
+    }
+}
+
+fn txn_value(events: &[Event], completed: bool) -> String {
+    let ops: Vec<String> = events.iter().map(|e| micro_op(e, completed)).collect();
+    format!("[{}]", ops.join(" "))
+}
+
+// Elle identifies a write by its value, so the writes an aborted attempt shares with a committed
+// retry are left out of its `:fail` op; otherwise reads of the retry would look like aborted reads.
+fn exported_events(transaction: &Transaction, committed: &HashSet<(usize, usize)>) -> Vec<Event> {
+    transaction
+        .events
+        .iter()
+        .filter(|e| transaction.success || !(e.write && committed.contains(&(e.variable, e.value))))
+        .cloned()
+        .collect()
+}
+
+/// Transactions executed by `run` are emitted in real-time order with their `:time`. Sessions
+/// without timestamps are emitted round by round instead: the `i`-th transactions of all sessions
+/// are invoked together and then completed. Only the process order of such a history is
+/// meaningful; do not check real-time models with it.
+pub fn write_elle<W: Write>(mut writer: W, sessions: &[Session]) -> io::Result<()> {
+    let committed: HashSet<(usize, usize)> = sessions
+        .iter()
+        .flatten()
+        .filter(|t| t.success)
+        .flat_map(|t| t.events.iter().filter(|e| e.write).map(|e| (e.variable, e.value)))
+        .collect();
+
+    // (time, completion, process, transaction)
+    let mut ops: Vec<(Option<u64>, bool, usize, &Transaction)> = Vec::new();
+    if sessions.iter().flatten().all(|t| t.time.is_some()) {
+        for (process, session) in sessions.iter().enumerate() {
+            for transaction in session.iter() {
+                let time = transaction.time.unwrap();
+                ops.push((Some(time.invoked), false, process, transaction));
+                ops.push((Some(time.completed), true, process, transaction));
+            }
+        }
+        // a completion at the same instant as an invocation goes first
+        ops.sort_by_key(|&(time, completion, _, _)| (time, !completion));
+    } else {
+        let n_round = sessions.iter().map(|s| s.len()).max().unwrap_or(0);
+        for round in 0..n_round {
+            for completion in [false, true] {
+                for (process, session) in sessions.iter().enumerate() {
+                    if let Some(transaction) = session.get(round) {
+                        ops.push((None, completion, process, transaction));
+                    }
+                }
+            }
+        }
+    }
+
+    for (index, (time, completion, process, transaction)) in ops.into_iter().enumerate() {
+        let events = exported_events(transaction, &committed);
+        let (kind, value) = match (completion, transaction.success) {
+            (false, _) => (":invoke", txn_value(&events, false)),
+            (true, true) => (":ok", txn_value(&events, true)),
+            (true, false) => (":fail", txn_value(&events, false)),
+        };
+        let time = time.map(|time| format!(", :time {}", time)).unwrap_or_default();
+        writeln!(
+            writer,
+            "{{:type {}, :f :txn, :value {}, :process {}{}, :index {}}}",
+            kind, value, process, time, index
+        )?;
+    }
+
+    writer.flush()
+}
+
+// (write, key, value), `None` for reads of `nil`
+fn parse_micro_op(op: &Edn) -> Option<(bool, usize, Option<usize>)> {
+    let (f, key, value) = match op.as_vec()? {
+        [f, key, value] => (f, key, value),
+        _ => return None,
+    };
+    let write = match f.as_keyword()? {
+        "r" => false,
+        "w" => true,
+        _ => return None,
+    };
+    let key = usize::try_from(key.as_int()?).ok()?;
+    let value = match value {
+        Edn::Nil if !write => None,
+        value => Some(usize::try_from(value.as_int()?).ok()?),
+    };
+    Some((write, key, value))
+}
+
+/// Micro-operations of a `:txn`, whether each writes, its key and its value.
+type MicroOps = Vec<(bool, usize, Option<usize>)>;
+
+/// Invocation of a process waiting for its completion, with its time.
+type Pending = Option<(MicroOps, Option<u64>)>;
+
+fn parse_txn_value(value: Option<&Edn>) -> Option<MicroOps> {
+    value?.as_vec()?.iter().map(parse_micro_op).collect()
+}
+
+/// Reads an rw-register history: either one operation map per form or a single vector of them.
+/// Every `:process` becomes a session, ordered by process number. `:ok` transactions commit with
+/// the values they read, `:fail` transactions abort. Indeterminate `:info` transactions (and
+/// invocations never completed) keep only their writes, and are taken as committed if any of
+/// those writes was read; they have no completion time. Operations other than `:txn` are
+/// ignored.
+pub fn read_elle<R: Read>(mut reader: R) -> Result<Vec<Session>, HistoryFileError> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+
+    let mut edn = EdnReader::new(&text);
+    let mut ops = Vec::new();
+    loop {
+        let line = edn.line();
+        match edn.read().map_err(|msg| HistoryFileError::Parse { line: edn.line(), msg })? {
+            Some(Edn::Vector(forms)) => ops.extend(forms.into_iter().map(|op| (line, op))),
+            Some(op) => ops.push((line, op)),
+            None => break,
+        }
+    }
+
+    // process -> (session, pending invocation)
+    let mut processes: BTreeMap<i64, (Session, Pending)> = BTreeMap::new();
+    let mut indeterminate: Vec<(i64, usize)> = Vec::new();
+
+    let maybe_committed = |micro_ops: MicroOps| Transaction {
+        events: micro_ops
+            .into_iter()
+            .filter_map(|(write, variable, value)| {
+                value.filter(|_| write).map(|value| Event { write, variable, value, success: false })
+            })
+            .collect(),
+        success: false,
+        aborts: Vec::new(),
+        time: None,
+    };
+
+    for (line, op) in ops {
+        let error = |msg: &str| HistoryFileError::Parse {
+            line,
+            msg: msg.to_string(),
+        };
+
+        if op.get("f").and_then(Edn::as_keyword) != Some("txn") {
+            continue;
+        }
+        let process = match op.get("process").and_then(Edn::as_int) {
+            Some(process) => process,
+            None => continue,
+        };
+        let micro_ops = parse_txn_value(op.get("value"))
+            .ok_or_else(|| error("expected a :value of micro-ops like [:r x v] or [:w x v] with non-negative integers"))?;
+        let time = op.get("time").and_then(Edn::as_int).and_then(|time| u64::try_from(time).ok());
+        let (session, pending) = processes.entry(process).or_default();
+
+        match op.get("type").and_then(Edn::as_keyword) {
+            Some("invoke") => {
+                if let Some((previous, _)) = pending.replace((micro_ops, time)) {
+                    indeterminate.push((process, session.len()));
+                    session.push(maybe_committed(previous));
+                }
+            }
+            Some(completion @ ("ok" | "fail" | "info")) => {
+                let (invoked, invoked_at) = pending.take().ok_or_else(|| error("completion without invocation"))?;
+                let time = invoked_at.zip(time).map(|(invoked, completed)| Timestamps { invoked, completed });
+                match completion {
+                    "ok" => session.push(Transaction {
+                        events: micro_ops
+                            .into_iter()
+                            .map(|(write, variable, value)| Event {
+                                write,
+                                variable,
+                                value: value.unwrap_or(0),
+                                success: true,
+                            })
+                            .collect(),
+                        success: true,
+                        aborts: Vec::new(),
+                        time,
+                    }),
+                    "fail" => session.push(Transaction {
+                        events: invoked
+                            .into_iter()
+                            .map(|(write, variable, value)| Event {
+                                write,
+                                variable,
+                                value: if write { value.unwrap_or(0) } else { 0 },
+                                success: false,
+                            })
+                            .collect(),
+                        success: false,
+                        aborts: Vec::new(),
+                        time,
+                    }),
+                    _ => {
+                        indeterminate.push((process, session.len()));
+                        session.push(maybe_committed(invoked));
+                    }
+                }
+            }
+            _ => return Err(error("expected :type :invoke, :ok, :fail or :info")),
+        }
+    }
+
+    for (&process, (session, pending)) in processes.iter_mut() {
+        if let Some((invoked, _)) = pending.take() {
+            indeterminate.push((process, session.len()));
+            session.push(maybe_committed(invoked));
+        }
+    }
+
+    let observed: HashSet<(usize, usize)> = processes
+        .values()
+        .flat_map(|(session, _)| session.iter())
+        .filter(|t| t.success)
+        .flat_map(|t| t.events.iter())
+        .filter(|e| !e.write)
+        .map(|e| (e.variable, e.value))
+        .collect();
+
+    for (process, i_txn) in indeterminate {
+        let transaction = &mut processes.get_mut(&process).unwrap().0[i_txn];
+        if transaction.events.iter().any(|e| observed.contains(&(e.variable, e.value))) {
+            transaction.success = true;
+            transaction.events.iter_mut().for_each(|e| e.success = true);
+        }
+    }
+
+    let sessions: Vec<Session> = processes.into_values().map(|(session, _)| session).collect();
+    check_unique_writes(&sessions)?;
+    Ok(sessions)
+}
@@ -0,0 +1,52 @@
+pub mod cobra;
+mod edn;
+pub mod elle;
+pub mod polysi;
+
+use std::collections::HashMap;
+
+use clap::ValueEnum;
+
+use crate::db::format::HistoryFileError;
+use crate::db::history::Session;
+use crate::verifier::is_retry_of;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Elle,
+    Cobra,
+    Polysi,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ImportFormat {
+    Elle,
+    Cobra,
+    Polysi,
+}
+
+/// The verifier identifies the writer of a read by its value, so every value may be written at
+/// most once per variable, apart from retries of aborted transactions. `0` is the initial value
+/// of every variable.
+pub fn check_unique_writes(sessions: &[Session]) -> Result<(), HistoryFileError> {
+    // (variable, value) -> (writer, committed)
+    let mut written = HashMap::new();
+    for (i_session, session) in sessions.iter().enumerate() {
+        for (i_txn, transaction) in session.iter().enumerate() {
+            for event in transaction.events.iter().filter(|e| e.write) {
+                let duplicate = match written.get(&(event.variable, event.value)) {
+                    Some(&(writer, committed)) => !is_retry_of(writer, committed, i_session + 1),
+                    None => false,
+                };
+                if event.value == 0 || duplicate {
+                    return Err(HistoryFileError::DuplicateWrite {
+                        variable: event.variable,
+                        value: event.value,
+                    });
+                }
+                written.insert((event.variable, event.value), ((i_session + 1, i_txn), transaction.success));
+            }
+        }
+    }
+    Ok(())
+}
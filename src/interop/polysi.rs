@@ -0,0 +1,90 @@
+//! PolySI text histories: one operation per line, `r(key,value,session,txn)` or
+//! `w(key,value,session,txn)`. Only committed transactions are part of the history.
+
+use std::io::{Read, Write};
+
+use crate::db::format::HistoryFileError;
+use crate::db::history::{Event, Session, Transaction};
+
+use super::check_unique_writes;
+
+pub fn write_polysi<W: Write>(mut writer: W, sessions: &[Session]) -> Result<(), HistoryFileError> {
+    for (i_session, session) in sessions.iter().enumerate() {
+        for (i_txn, transaction) in session.iter().enumerate().filter(|(_, t)| t.success) {
+            for event in transaction.events.iter().filter(|e| e.success) {
+                writeln!(
+                    writer,
+                    "{}({},{},{},{})",
+                    if event.write { 'w' } else { 'r' },
+                    event.variable,
+                    event.value,
+                    i_session,
+                    i_txn
+                )?;
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn read_polysi<R: Read>(mut reader: R) -> Result<Vec<Session>, HistoryFileError> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+
+    let mut sessions: Vec<Session> = Vec::new();
+    // transaction id in the file of the last transaction of every session
+    let mut last_txn: Vec<Option<usize>> = Vec::new();
+
+    for (i_line, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let error = || HistoryFileError::Parse {
+            line: i_line + 1,
+            msg: "expected `r(key,value,session,txn)` or `w(key,value,session,txn)`".to_string(),
+        };
+
+        let write = match line.chars().next() {
+            Some('w') => true,
+            Some('r') => false,
+            _ => return Err(error()),
+        };
+        let fields: Vec<usize> = line[1..]
+            .strip_prefix('(')
+            .and_then(|rest| rest.strip_suffix(')'))
+            .ok_or_else(error)?
+            .split(',')
+            .map(|field| field.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| error())?;
+        let (variable, value, i_session, txn) = match fields[..] {
+            [variable, value, i_session, txn] => (variable, value, i_session, txn),
+            _ => return Err(error()),
+        };
+
+        if sessions.len() <= i_session {
+            sessions.resize(i_session + 1, Vec::new());
+            last_txn.resize(i_session + 1, None);
+        }
+        if last_txn[i_session] != Some(txn) {
+            sessions[i_session].push(Transaction {
+                events: Vec::new(),
+                success: true,
+                aborts: Vec::new(),
+                time: None,
+            });
+            last_txn[i_session] = Some(txn);
+        }
+        sessions[i_session].last_mut().unwrap().events.push(Event {
+            write,
+            variable,
+            value,
+            success: true,
+        });
+    }
+
+    check_unique_writes(&sessions)?;
+    Ok(sessions)
+}
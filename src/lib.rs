@@ -0,0 +1,5 @@
+pub mod clients;
+pub mod consistency;
+pub mod db;
+pub mod interop;
+pub mod verifier;
@@ -1,50 +1,32 @@
-mod clients;
-mod db;
-
 use clap::{Parser, Subcommand, ValueEnum};
-use clients::{DynCluster, DynNode, MemgraphCluster, PostgresCluster, PostgresSERCluster, DGraphCluster, GaleraCluster, MySQLCluster};
-use db::cluster::Cluster;
+use dbcop::clients::{AntidoteCluster, CockroachCluster, DynCluster, DynNode, InMemoryCluster, InMemoryMode, MemgraphCluster, PostgresCluster, DGraphCluster, MySQLCluster, TiDBCluster, YugabyteCluster};
+use dbcop::db::cluster::{Cluster, Init, Isolation, Placement};
 use std::fs::File;
-use std::io::{BufReader, BufWriter};
-
-use rand::distributions::{Bernoulli, Distribution, Uniform};
+use std::io::{BufReader, BufWriter, Write};
+use std::process;
+use std::time::{Duration, Instant};
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use std::fs;
 
-use db::distribution::{MyDistribution, MyDistributionTrait};
-use db::history::{generate_mult_histories, HistoryParams};
-use db::history::History;
+use dbcop::db::distribution::KeyDistribution;
+use dbcop::db::history::{generate_mult_histories, HistParams, History, HistoryParams, Session};
+use dbcop::db::manifest::Status;
+use dbcop::db::retry::RetryPolicy;
 
-use zipf::ZipfDistribution;
-
-struct HotspotDistribution {
-    hot_probability: Bernoulli,
-    hot_key: Uniform<usize>,
-    non_hot_key: Uniform<usize>,
-}
+use chrono::{Local, TimeZone};
+use dbcop::db::format::{dump_history, dump_history_as, load_history, load_history_as, HistoryFormat};
 
-impl Distribution<usize> for HotspotDistribution {
-    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> usize {
-        if self.hot_probability.sample(rng) {
-            self.hot_key.sample(rng)
-        } else {
-            self.non_hot_key.sample(rng)
-        }
-    }
-}
+use dbcop::consistency::Consistency;
+use dbcop::interop::cobra::{read_cobra, write_cobra};
+use dbcop::interop::elle::{read_elle, write_elle};
+use dbcop::interop::polysi::{read_polysi, write_polysi};
+use dbcop::interop::{ExportFormat, ImportFormat};
+use dbcop::verifier::shrink::shrink;
+use dbcop::verifier::Verifier;
 
-impl HotspotDistribution {
-    fn new(n_variables: usize) -> HotspotDistribution {
-        let hot_key_max = n_variables / 5;
-        HotspotDistribution {
-            hot_probability: Bernoulli::new(0.8).unwrap(),
-            hot_key: Uniform::new(0, hot_key_max),
-            non_hot_key: Uniform::new(hot_key_max, n_variables),
-        }
-    }
-}
+use serde::Serialize;
 
 #[derive(Parser)]
 #[clap(name = "dbcop", author = "Ranadeep", about = "Generates histories or verifies executed histories")]
@@ -63,7 +45,7 @@ enum Commands {
         #[clap(long = "nhist", default_value_t = 1, help = "Number of histories to generate")]
         n_history: usize,
 
-        #[clap(long = "nnode", short = 'n', help = "Number of nodes per history")]
+        #[clap(long = "nnode", short = 'n', visible_alias = "nsession", help = "Number of sessions per history, independent of the nodes it is run on")]
         n_node: usize,
 
         #[clap(long = "nvar", short = 'v', help = "Number of variables per history")]
@@ -89,6 +71,9 @@ enum Commands {
 
         #[clap(long, action, help = "Randomize size of transactions")]
         random_txn_size: bool,
+
+        #[clap(long, help = "Seed for the random generator, chosen randomly if absent")]
+        seed: Option<u64>,
     },
     Print {
         #[clap(short = 'd', help = "Directory containing executed history")]
@@ -107,17 +92,216 @@ enum Commands {
 
         #[clap(long = "db", value_enum)]
         database: Database,
+
+        #[clap(value_enum, long, help = "Isolation level of the transactions [default: repeatable-read for postgres, yugabyte and tidb, otherwise serializable]")]
+        isolation: Option<Isolation>,
+
+        #[clap(value_enum, long, help = "Create every variable before the run (eager) or by its first write (lazy) [default: lazy for postgres, otherwise eager]")]
+        init: Option<Init>,
+
+        #[clap(long, default_value_t = Placement::RoundRobin, help = "Node of every session: round-robin, random or pinned:<node>,<node>,...")]
+        placement: Placement,
+
+        #[clap(long, action, help = "Continue a previous run, only executing histories which did not complete")]
+        resume: bool,
+
+        #[clap(long, default_value_t = 100, help = "Attempts before a transaction is left aborted, 0 for no limit")]
+        max_attempts: usize,
+
+        #[clap(long, help = "Milliseconds a statement may run before the database aborts it, SQL databases only")]
+        statement_timeout: Option<u64>,
+
+        #[clap(long = "txn-timeout", help = "Milliseconds an attempt may run before it is rolled back")]
+        transaction_timeout: Option<u64>,
+
+        #[clap(long, default_value_t = 10, help = "Milliseconds to wait before the first retry, doubled on every further abort")]
+        backoff: u64,
+
+        #[clap(long, default_value_t = 1000, help = "Longest wait between retries in milliseconds")]
+        max_backoff: u64,
+
+        #[clap(long, action, help = "Keep every aborted attempt as an aborted transaction with the values it read")]
+        record_aborted: bool,
+
+        #[clap(long, action, help = "AntidoteDB: don't start a transaction after the commit of the previous one in its session")]
+        no_timestamp_propagation: bool,
+
+        #[clap(value_enum, long, help = "in-memory: consistency of the store, or the anomaly it produces [default: ser]")]
+        mode: Option<InMemoryMode>,
+
+        #[clap(long, help = "Seed for the backoff between retries and the in-memory store [default: the seed of each history]")]
+        seed: Option<u64>,
+    },
+    #[clap(about = "Verify executed histories")]
+    Verify {
+        #[clap(long = "ver_dir", short = 'd', help = "Directory containing executed histories")]
+        v_directory: PathBuf,
+
+        #[clap(long = "out_dir", short = 'o', help = "Directory to write result_log.json")]
+        o_directory: Option<PathBuf>,
+
+        #[clap(value_enum, long = "cons", short = 'c', required_unless_present = "classify", help = "Consistency level to verify")]
+        consistency: Option<Consistency>,
+
+        #[clap(long, action, conflicts_with = "consistency", help = "Check every level from the weakest up to the first violated one")]
+        classify: bool,
+    },
+    #[clap(about = "Shrink a history to a smaller one violating the same consistency level")]
+    Shrink {
+        #[clap(help = "History file to read")]
+        input: PathBuf,
+
+        #[clap(help = "Directory to write history.bincode and history.txt")]
+        output: PathBuf,
+
+        #[clap(value_enum, long, help = "Input format, guessed from the extension if absent")]
+        from: Option<HistoryFormat>,
+
+        #[clap(value_enum, long = "cons", short = 'c', help = "Consistency level to verify")]
+        consistency: Consistency,
+    },
+    #[clap(about = "Convert a history between bincode, json and text formats")]
+    Convert {
+        #[clap(help = "History file to read")]
+        input: PathBuf,
+
+        #[clap(help = "History file to write")]
+        output: PathBuf,
+
+        #[clap(value_enum, long, help = "Input format, guessed from the extension if absent")]
+        from: Option<HistoryFormat>,
+
+        #[clap(value_enum, long, help = "Output format, guessed from the extension if absent")]
+        to: Option<HistoryFormat>,
+    },
+    #[clap(about = "Export an executed history for other checkers")]
+    Export {
+        #[clap(help = "History file to read")]
+        input: PathBuf,
+
+        #[clap(help = "File to write, a directory for cobra")]
+        output: PathBuf,
+
+        #[clap(value_enum, long, help = "Input format, guessed from the extension if absent")]
+        from: Option<HistoryFormat>,
+
+        #[clap(value_enum, long = "format", help = "Format to export to")]
+        format: ExportFormat,
+    },
+    #[clap(about = "Import a history recorded by other checkers")]
+    Import {
+        #[clap(help = "File to read, a directory for cobra")]
+        input: PathBuf,
+
+        #[clap(help = "History file to write")]
+        output: PathBuf,
+
+        #[clap(value_enum, long = "format", help = "Format to import from")]
+        format: ImportFormat,
+
+        #[clap(value_enum, long, help = "Output format, guessed from the extension if absent")]
+        to: Option<HistoryFormat>,
     }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
-enum KeyDistribution {
-    Uniform, Zipf, Hotspot
+enum Database {
+    Memgraph, Postgres, Dgraph, Mysql, Tidb, Yugabyte, Cockroachdb, InMemory,
+    Antidote,
+    // names of the former per-isolation drivers, kept with their isolation level and init
+    #[clap(hide = true)]
+    PostgresSer,
+    #[clap(hide = true)]
+    YugabyteSer,
+    #[clap(hide = true)]
+    Galera,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
-enum Database {
-    Memgraph, Postgres, PostgresSer, Dgraph, Galera, Mysql
+impl Database {
+    /// Isolation levels transactions can be run at, weakest first; empty for the databases without
+    /// `--isolation` and `--init`.
+    fn isolation_levels(&self) -> &'static [Isolation] {
+        match self {
+            Database::Memgraph | Database::Dgraph | Database::InMemory => &[],
+            Database::Antidote => &[],
+            // TiDB runs in optimistic mode, where read committed has no effect
+            Database::Tidb => &[Isolation::RepeatableRead],
+            Database::Cockroachdb => &[Isolation::Serializable],
+            Database::Postgres
+            | Database::PostgresSer
+            | Database::Mysql
+            | Database::Galera
+            | Database::Yugabyte
+            | Database::YugabyteSer => &[Isolation::ReadCommitted, Isolation::RepeatableRead, Isolation::Serializable],
+        }
+    }
+
+    /// Whether the database is run over SQL, the only drivers which can limit statements by
+    /// `--statement-timeout`.
+    fn is_sql(&self) -> bool {
+        !self.isolation_levels().is_empty()
+    }
+
+    /// Isolation level and init without `--isolation` and `--init`, those the database was run
+    /// at before the options existed.
+    fn defaults(&self) -> (Isolation, Init) {
+        match self {
+            Database::Postgres => (Isolation::RepeatableRead, Init::Lazy),
+            Database::Galera | Database::Tidb | Database::Yugabyte => (Isolation::RepeatableRead, Init::Eager),
+            _ => (Isolation::Serializable, Init::Eager),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct VerifyResult {
+    hist: String,
+    duration: f64,
+    consistency: Consistency,
+    violation: Option<Consistency>,
+}
+
+#[derive(Serialize)]
+struct ClassifyResult {
+    hist: String,
+    duration: f64,
+    strongest: Option<Consistency>,
+    violation: Option<Consistency>,
+}
+
+// either a single executed history or the output directory of `run`
+fn executed_histories(dir: &Path) -> Vec<PathBuf> {
+    if dir.join("history.bincode").is_file() {
+        return vec![dir.to_path_buf()];
+    }
+
+    let mut dirs: Vec<PathBuf> = fs::read_dir(dir)
+        .expect("couldn't read directory")
+        .filter_map(|entry_res| match entry_res {
+            Ok(entry) if entry.path().join("history.bincode").is_file() => Some(entry.path()),
+            _ => None,
+        })
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+fn print_counterexample(sessions: &[Session], level: Consistency) {
+    match Verifier::counterexample(sessions, level) {
+        Some(counterexample) => {
+            for line in format!("{:?}", counterexample).lines() {
+                println!("    {}", line);
+            }
+        }
+        None => println!("    no cycle without guessing an order of writes"),
+    }
+}
+
+fn format_of(path: &Path, format: Option<HistoryFormat>) -> HistoryFormat {
+    format.or_else(|| HistoryFormat::from_path(path)).unwrap_or_else(|| {
+        eprintln!("couldn't guess the format of {:?}, use --from/--to", path);
+        process::exit(1);
+    })
 }
 
 fn main() {
@@ -126,30 +310,18 @@ fn main() {
     match cli.command {
         Commands::Print { directory } => {
             let v_path = directory.join("history.bincode");
-            let file = File::open(v_path).unwrap();
-            let buf_reader = BufReader::new(file);
-            let hist: History = bincode::deserialize_from(buf_reader).unwrap();
+            let hist = load_history(&v_path).unwrap_or_else(|e| {
+                eprintln!("couldn't load {:?}: {}", v_path, e);
+                process::exit(1);
+            });
 
             println!("{:?}", hist);
         }
-        Commands::Generate { g_directory, n_history, n_node, n_variable, n_transaction, n_event, read_probability, key_distribution, longtxn_proportion, longtxn_size, random_txn_size } => {
+        Commands::Generate { g_directory, n_history, n_node, n_variable, n_transaction, n_event, read_probability, key_distribution, longtxn_proportion, longtxn_size, random_txn_size, seed } => {
             if !g_directory.is_dir() {
                 fs::create_dir_all(&g_directory).expect("failed to create directory");
             }
 
-            let distribution: Box<dyn MyDistributionTrait> =
-                match key_distribution {
-                    KeyDistribution::Uniform => Box::new(MyDistribution::new(Uniform::new(0, n_variable))),
-                    KeyDistribution::Zipf => Box::new(MyDistribution::new(
-                        ZipfDistribution::new(n_variable, 0.5)
-                            .unwrap()
-                            .map(|x| x - 1),
-                    )),
-                    KeyDistribution::Hotspot => {
-                        Box::new(MyDistribution::new(HotspotDistribution::new(n_variable)))
-                    }
-                };
-
             let mut histories = generate_mult_histories(
                 HistoryParams {
                     n_hist: n_history,
@@ -158,35 +330,277 @@ fn main() {
                     n_transaction,
                     n_event,
                     read_probability,
-                    key_distribution: distribution.as_ref(),
+                    key_distribution,
                     longtxn_proportion,
                     longtxn_size,
                     random_txn_size,
+                    seed: seed.unwrap_or_else(rand::random),
                 }
             );
 
             for hist in histories.drain(..) {
-                let file = File::create(g_directory.join(format!("hist-{:05}.bincode", hist.get_id())))
-                    .expect("couldn't create bincode file");
-                let buf_writer = BufWriter::new(file);
-                bincode::serialize_into(buf_writer, &hist)
+                dump_history(&g_directory.join(format!("hist-{:05}.bincode", hist.get_id())), &hist)
                     .expect("dumping history to bincode file went wrong");
             }
         }
-        Commands::Run { hist_dir, hist_out, addrs, database } => {
+        Commands::Run {
+            hist_dir,
+            hist_out,
+            addrs,
+            database,
+            isolation,
+            init,
+            placement,
+            resume,
+            max_attempts,
+            statement_timeout,
+            transaction_timeout,
+            backoff,
+            max_backoff,
+            record_aborted,
+            no_timestamp_propagation,
+            mode,
+            seed,
+        } => {
+
+            let retry = RetryPolicy {
+                max_attempts: Some(max_attempts).filter(|&n| n > 0),
+                statement_timeout: statement_timeout.map(Duration::from_millis),
+                transaction_timeout: transaction_timeout.map(Duration::from_millis),
+                backoff: Duration::from_millis(backoff),
+                max_backoff: Duration::from_millis(max_backoff),
+                seed,
+                record_aborted,
+            };
+
+            let levels = database.isolation_levels();
+            if levels.is_empty() && (isolation.is_some() || init.is_some()) {
+                eprintln!("--isolation and --init are not supported by this database");
+                process::exit(1);
+            }
+            if let Some(level) = isolation.filter(|level| !levels.contains(level)) {
+                let levels: Vec<String> = levels.iter().map(|level| level.to_string()).collect();
+                eprintln!("{} is not supported by this database, use one of {}", level, levels.join(", "));
+                process::exit(1);
+            }
+            if no_timestamp_propagation && database != Database::Antidote {
+                eprintln!("--no-timestamp-propagation is only supported by antidote");
+                process::exit(1);
+            }
+            if statement_timeout.is_some() && !database.is_sql() {
+                eprintln!("--statement-timeout is not supported by this database");
+                process::exit(1);
+            }
+            if mode.is_some() && database != Database::InMemory {
+                eprintln!("--mode is only supported by in-memory");
+                process::exit(1);
+            }
+            let (default_isolation, default_init) = database.defaults();
+            let isolation = isolation.unwrap_or(default_isolation);
+            let init = init.unwrap_or(default_init);
+
             fs::create_dir_all(&hist_out).expect("couldn't create directory");
             let addrs_str = addrs.iter().map(|addr| addr.as_str()).collect();
 
             let mut cluster: Box<dyn Cluster<DynNode>> = match database {
                 Database::Memgraph => Box::new(DynCluster::new(MemgraphCluster::new(&addrs_str))),
-                Database::Postgres => Box::new(DynCluster::new(PostgresCluster::new(&addrs_str))),
-                Database::PostgresSer => Box::new(DynCluster::new(PostgresSERCluster::new(&addrs_str))),
+                Database::Postgres | Database::PostgresSer => Box::new(DynCluster::new(PostgresCluster::new(&addrs_str, isolation, init))),
                 Database::Dgraph => Box::new(DynCluster::new(DGraphCluster::new(&addrs_str))),
-                Database::Galera => Box::new(DynCluster::new(GaleraCluster::new(&addrs_str))),
-                Database::Mysql => Box::new(DynCluster::new(MySQLCluster::new(&addrs_str))),
+                Database::Mysql => Box::new(DynCluster::new(MySQLCluster::new(&addrs_str, isolation, init))),
+                Database::Galera => Box::new(DynCluster::new(MySQLCluster::galera(&addrs_str, isolation, init))),
+                Database::Tidb => Box::new(DynCluster::new(TiDBCluster::new(&addrs_str, isolation, init))),
+                Database::Yugabyte | Database::YugabyteSer => Box::new(DynCluster::new(YugabyteCluster::new(&addrs_str, isolation, init))),
+                Database::Cockroachdb => Box::new(DynCluster::new(CockroachCluster::new(&addrs_str, isolation, init))),
+                Database::Antidote => Box::new(DynCluster::new(AntidoteCluster::new(&addrs_str, !no_timestamp_propagation))),
+                Database::InMemory => Box::new(DynCluster::new(InMemoryCluster::new(mode.unwrap_or_default(), seed))),
             };
 
-            cluster.execute_all(&hist_dir.as_path(), &hist_out.as_path(), 100);
+            if let Err(e) = placement.validate(cluster.n_node()) {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+            // nothing to settle between histories without a database
+            let pause = if database == Database::InMemory { 0 } else { 100 };
+
+            let manifest = cluster
+                .execute_all(hist_dir.as_path(), hist_out.as_path(), pause, &placement, &retry, resume)
+                .unwrap_or_else(|e| {
+                    eprintln!("couldn't run {:?}: {}", hist_dir, e);
+                    process::exit(1);
+                });
+
+            println!(
+                "{} done, {} failed",
+                manifest.count(Status::Done),
+                manifest.count(Status::Failed)
+            );
+            for (name, entry) in manifest.entries().filter(|(_, e)| e.status == Status::Failed) {
+                eprintln!("{}: {}", name, entry.error.as_deref().unwrap_or("unknown error"));
+            }
+            if manifest.count(Status::Failed) > 0 {
+                process::exit(1);
+            }
+        }
+        Commands::Verify { v_directory, o_directory, consistency, .. } => {
+            let mut result_log = o_directory.map(|dir| {
+                fs::create_dir_all(&dir).expect("couldn't create directory");
+                BufWriter::new(File::create(dir.join("result_log.json")).expect("couldn't create result log"))
+            });
+
+            for dir in executed_histories(&v_directory) {
+                let hist = match load_history(&dir.join("history.bincode")) {
+                    Ok(hist) => hist,
+                    Err(e) => {
+                        eprintln!("skipping {:?}: {}", dir, e);
+                        continue;
+                    }
+                };
+
+                let name = dir.file_name().map_or(String::new(), |n| n.to_string_lossy().into_owned());
+                let start_time = Instant::now();
+
+                let result = match consistency {
+                    Some(consistency) => {
+                        let violation = Verifier::new(consistency).verify(hist.get_data());
+                        let duration = start_time.elapsed().as_secs_f64();
+
+                        match violation {
+                            None => println!("{} {}: ok", name, consistency),
+                            Some(level) => {
+                                println!("{} {}: violates {}", name, consistency, level);
+                                print_counterexample(hist.get_data(), level);
+                            }
+                        }
+                        serde_json::to_value(VerifyResult { hist: name, duration, consistency, violation })
+                    }
+                    // --classify
+                    None => {
+                        let (strongest, violation) = Verifier::classify(hist.get_data());
+                        let duration = start_time.elapsed().as_secs_f64();
+
+                        let strongest_repr = strongest.map_or("none".to_string(), |level| level.to_string());
+                        match violation {
+                            None => println!("{}: {}", name, strongest_repr),
+                            Some(level) => {
+                                println!("{}: {}, violates {}", name, strongest_repr, level);
+                                print_counterexample(hist.get_data(), level);
+                            }
+                        }
+                        serde_json::to_value(ClassifyResult { hist: name, duration, strongest, violation })
+                    }
+                };
+
+                if let Some(writer) = result_log.as_mut() {
+                    let result = result.expect("couldn't write result log");
+                    serde_json::to_writer(&mut *writer, &result).expect("couldn't write result log");
+                    writeln!(writer).expect("couldn't write result log");
+                }
+            }
+        }
+        Commands::Shrink { input, output, from, consistency } => {
+            let hist = load_history_as(format_of(&input, from), &input).unwrap_or_else(|e| {
+                eprintln!("couldn't load {:?}: {}", input, e);
+                process::exit(1);
+            });
+
+            let verifier = Verifier::new(consistency);
+            let level = verifier.verify(hist.get_data()).unwrap_or_else(|| {
+                eprintln!("{:?} satisfies {}, nothing to shrink", input, consistency);
+                process::exit(1);
+            });
+
+            // keep the violated level, not just any violation
+            let data = shrink(hist.get_data(), |sessions| verifier.verify(sessions) == Some(level));
+
+            let n_transaction = |data: &[Session]| data.iter().map(|session| session.len()).sum::<usize>();
+            println!(
+                "violates {} with {} transactions in {} sessions, from {} in {}",
+                level,
+                n_transaction(&data),
+                data.len(),
+                n_transaction(hist.get_data()),
+                hist.get_data().len()
+            );
+            print_counterexample(&data, level);
+
+            let mut params = HistParams::from_sessions(&data);
+            params.set_id(hist.get_id());
+            let shrunk = History::new(
+                params,
+                format!("{}, shrunk", hist.get_info()),
+                hist.get_start(),
+                hist.get_end(),
+                data,
+            );
+
+            fs::create_dir_all(&output).expect("couldn't create directory");
+            for (name, format) in [("history.bincode", HistoryFormat::Bincode), ("history.txt", HistoryFormat::Text)] {
+                dump_history_as(format, &output.join(name), &shrunk).unwrap_or_else(|e| {
+                    eprintln!("couldn't write {:?}: {}", output.join(name), e);
+                    process::exit(1);
+                });
+            }
+        }
+        Commands::Convert { input, output, from, to } => {
+            let hist = load_history_as(format_of(&input, from), &input).unwrap_or_else(|e| {
+                eprintln!("couldn't load {:?}: {}", input, e);
+                process::exit(1);
+            });
+
+            dump_history_as(format_of(&output, to), &output, &hist).unwrap_or_else(|e| {
+                eprintln!("couldn't write {:?}: {}", output, e);
+                process::exit(1);
+            });
+        }
+        Commands::Export { input, output, from, format } => {
+            let hist = load_history_as(format_of(&input, from), &input).unwrap_or_else(|e| {
+                eprintln!("couldn't load {:?}: {}", input, e);
+                process::exit(1);
+            });
+
+            let result = match format {
+                ExportFormat::Elle => File::create(&output)
+                    .and_then(|file| write_elle(BufWriter::new(file), hist.get_data()))
+                    .map_err(From::from),
+                ExportFormat::Cobra => write_cobra(&output, hist.get_data()),
+                ExportFormat::Polysi => File::create(&output)
+                    .map_err(From::from)
+                    .and_then(|file| write_polysi(BufWriter::new(file), hist.get_data())),
+            };
+
+            result.unwrap_or_else(|e| {
+                eprintln!("couldn't write {:?}: {}", output, e);
+                process::exit(1);
+            });
+        }
+        Commands::Import { input, output, format, to } => {
+            let data = match format {
+                ImportFormat::Elle => File::open(&input)
+                    .map_err(From::from)
+                    .and_then(|file| read_elle(BufReader::new(file))),
+                ImportFormat::Cobra => read_cobra(&input),
+                ImportFormat::Polysi => File::open(&input)
+                    .map_err(From::from)
+                    .and_then(|file| read_polysi(BufReader::new(file))),
+            }
+            .unwrap_or_else(|e| {
+                eprintln!("couldn't import {:?}: {}", input, e);
+                process::exit(1);
+            });
+
+            let epoch = Local.timestamp_opt(0, 0).unwrap();
+            let hist = History::new(
+                HistParams::from_sessions(&data),
+                format!("imported from {:?}", format),
+                epoch,
+                epoch,
+                data,
+            );
+
+            dump_history_as(format_of(&output, to), &output, &hist).unwrap_or_else(|e| {
+                eprintln!("couldn't write {:?}: {}", output, e);
+                process::exit(1);
+            });
         }
     }
 }
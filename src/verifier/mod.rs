@@ -0,0 +1,481 @@
+pub mod shrink;
+#[cfg(test)]
+mod test_util;
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::consistency::algo::{
+    check_causal, check_parallel_snapshot_isolation, check_prefix, check_read_atomic, check_read_committed,
+    check_serializable, check_snapshot_isolation, check_strict_serializable,
+};
+use crate::consistency::util::{AtomicHistory, TransactionId, TransactionInfo};
+use crate::consistency::witness::{find_cycle, Dependency};
+use crate::consistency::Consistency;
+use crate::db::history::{Event, Session};
+
+/// Anomalies found while computing the write-read relation. They make a history inconsistent at
+/// every level, except non-repeatable reads, which read committed allows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Anomaly {
+    /// two writes of the same value to a variable
+    DuplicateWrite { variable: usize, value: usize },
+    /// read a value nobody wrote
+    ThinAirRead { txn: TransactionId, variable: usize, value: usize },
+    /// read a value written by an aborted transaction
+    AbortedRead { txn: TransactionId, variable: usize, value: usize },
+    /// read a value later overwritten by the same writing transaction
+    IntermediateRead { txn: TransactionId, variable: usize, value: usize },
+    /// read a value the transaction itself writes later
+    FutureRead { txn: TransactionId, variable: usize, value: usize },
+    /// read a value other than the transaction's own last write
+    InconsistentInternalRead { txn: TransactionId, variable: usize, value: usize },
+    /// two external reads of a variable in a transaction returned different values
+    NonRepeatableRead { txn: TransactionId, variable: usize },
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Anomaly::DuplicateWrite { variable, value } => {
+                write!(f, "value {} written twice to variable {}", value, variable)
+            }
+            Anomaly::ThinAirRead { txn, variable, value } => {
+                write!(f, "{:?} read unwritten value {} of variable {}", txn, value, variable)
+            }
+            Anomaly::AbortedRead { txn, variable, value } => {
+                write!(f, "{:?} read aborted value {} of variable {}", txn, value, variable)
+            }
+            Anomaly::IntermediateRead { txn, variable, value } => {
+                write!(f, "{:?} read intermediate value {} of variable {}", txn, value, variable)
+            }
+            Anomaly::FutureRead { txn, variable, value } => {
+                write!(f, "{:?} read its own future value {} of variable {}", txn, value, variable)
+            }
+            Anomaly::InconsistentInternalRead { txn, variable, value } => {
+                write!(f, "{:?} read {} of variable {} instead of its own write", txn, value, variable)
+            }
+            Anomaly::NonRepeatableRead { txn, variable } => {
+                write!(f, "{:?} read different values of variable {}", txn, variable)
+            }
+        }
+    }
+}
+
+/// A committed transaction of a cycle, identified like `TransactionId` by its session, `0` for
+/// the initial transaction, and its index in the executed session.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Step {
+    pub session: usize,
+    pub index: usize,
+    /// events of the transaction, the writes of the involved variables for the initial one
+    pub events: Vec<Event>,
+    /// dependency of the next transaction of the cycle on this one
+    pub dependency: Dependency,
+}
+
+/// Why a history violates a consistency level.
+#[derive(Clone, PartialEq, Eq)]
+pub enum Counterexample {
+    Anomaly(Anomaly),
+    /// the last step depends on the first
+    Cycle(Vec<Step>),
+}
+
+impl fmt::Debug for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} {:?} -{}->", (self.session, self.index), self.events, self.dependency)
+    }
+}
+
+impl fmt::Debug for Counterexample {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Counterexample::Anomaly(anomaly) => write!(f, "{}", anomaly),
+            Counterexample::Cycle(steps) => {
+                for (i, step) in steps.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{:?}", step)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+struct WriteInfo {
+    // session and index in the executed history
+    txn: TransactionId,
+    committed: bool,
+    last: bool,
+}
+
+/// A value may be written again by a later attempt of an aborted transaction, which stays in the
+/// session of its earlier attempts. Reads of such a value are taken to read the later attempt.
+pub fn is_retry_of(writer: TransactionId, committed: bool, session: usize) -> bool {
+    !committed && writer.0 == session
+}
+
+/// Builds the write-read relation of the committed transactions in `sessions`. Written values
+/// must be unique per variable, except for recorded aborted attempts of a transaction; the
+/// initial value of every variable is `0`.
+pub fn atomic_history(sessions: &[Session]) -> Result<AtomicHistory, Anomaly> {
+    build_history(sessions, true)
+}
+
+/// Like `atomic_history`, but a transaction may read different values of a variable, as read
+/// committed allows. `TransactionInfo::read` then holds the first read of every variable.
+pub fn read_committed_history(sessions: &[Session]) -> Result<AtomicHistory, Anomaly> {
+    build_history(sessions, false)
+}
+
+fn build_history(sessions: &[Session], repeatable_reads: bool) -> Result<AtomicHistory, Anomaly> {
+    let mut write_map: HashMap<(usize, usize), WriteInfo> = HashMap::new();
+
+    for (i_session, session) in sessions.iter().enumerate() {
+        for (i_txn, transaction) in session.iter().enumerate() {
+            let mut last_write = HashMap::new();
+            for event in transaction.events.iter().filter(|e| e.write && e.success) {
+                let key = (event.variable, event.value);
+                let duplicate = match write_map.get(&key) {
+                    Some(w) => !is_retry_of(w.txn, w.committed, i_session + 1),
+                    None => false,
+                };
+                if event.value == 0 || duplicate {
+                    return Err(Anomaly::DuplicateWrite {
+                        variable: event.variable,
+                        value: event.value,
+                    });
+                }
+                if let Some(prev) = last_write.insert(event.variable, key) {
+                    write_map.get_mut(&prev).unwrap().last = false;
+                }
+                write_map.insert(
+                    key,
+                    WriteInfo {
+                        txn: (i_session + 1, i_txn),
+                        committed: transaction.success,
+                        last: true,
+                    },
+                );
+            }
+        }
+    }
+
+    // position of every committed transaction in its session of the atomic history
+    let positions: HashMap<TransactionId, TransactionId> = sessions
+        .iter()
+        .enumerate()
+        .flat_map(|(i_session, session)| {
+            session
+                .iter()
+                .enumerate()
+                .filter(|(_, t)| t.success)
+                .enumerate()
+                .map(move |(pos, (i_txn, _))| ((i_session + 1, i_txn), (i_session + 1, pos)))
+        })
+        .collect();
+
+    let mut history = AtomicHistory {
+        sessions: vec![vec![TransactionInfo::default()]],
+    };
+
+    for (i_session, session) in sessions.iter().enumerate() {
+        let mut txns = Vec::new();
+        for (i_txn, transaction) in session.iter().enumerate().filter(|(_, t)| t.success) {
+            let id = (i_session + 1, i_txn);
+            let mut info = TransactionInfo {
+                index: i_txn,
+                time: transaction.time,
+                ..Default::default()
+            };
+            let mut local: HashMap<usize, usize> = HashMap::new();
+            let mut external: HashMap<usize, usize> = HashMap::new();
+
+            for event in transaction.events.iter().filter(|e| e.success) {
+                if event.write {
+                    local.insert(event.variable, event.value);
+                    info.write.insert(event.variable);
+                    continue;
+                }
+
+                let (variable, value) = (event.variable, event.value);
+
+                if let Some(&own) = local.get(&variable) {
+                    if own != value {
+                        return Err(Anomaly::InconsistentInternalRead { txn: id, variable, value });
+                    }
+                    continue;
+                }
+
+                let first = match external.entry(variable) {
+                    Entry::Occupied(entry) => {
+                        if *entry.get() == value {
+                            continue;
+                        }
+                        if repeatable_reads {
+                            return Err(Anomaly::NonRepeatableRead { txn: id, variable });
+                        }
+                        false
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert(value);
+                        true
+                    }
+                };
+
+                let writer = if value == 0 {
+                    (0, 0)
+                } else {
+                    match write_map.get(&(variable, value)) {
+                        None => return Err(Anomaly::ThinAirRead { txn: id, variable, value }),
+                        Some(w) if w.txn == id => {
+                            return Err(Anomaly::FutureRead { txn: id, variable, value })
+                        }
+                        Some(w) if !w.committed => {
+                            return Err(Anomaly::AbortedRead { txn: id, variable, value })
+                        }
+                        Some(w) if !w.last => {
+                            return Err(Anomaly::IntermediateRead { txn: id, variable, value })
+                        }
+                        Some(w) => positions[&w.txn],
+                    }
+                };
+
+                if first {
+                    info.read.insert(variable, writer);
+                }
+                info.reads.push((variable, writer));
+            }
+
+            txns.push(info);
+        }
+        history.sessions.push(txns);
+    }
+
+    history.sessions[0][0].write = history
+        .ids()
+        .flat_map(|id| history.txn(id).read.keys().copied().collect::<Vec<_>>())
+        .chain(write_map.keys().map(|&(variable, _)| variable))
+        .collect();
+
+    Ok(history)
+}
+
+pub struct Verifier {
+    consistency_model: Consistency,
+}
+
+impl Verifier {
+    pub fn new(consistency_model: Consistency) -> Self {
+        Verifier { consistency_model }
+    }
+
+    pub fn model(&mut self, consistency_model: Consistency) {
+        self.consistency_model = consistency_model;
+    }
+
+    pub fn get_model(&self) -> Consistency {
+        self.consistency_model
+    }
+
+    /// Checks the history against the chosen model and every level it implies, returning the
+    /// weakest violated level, or `None` if the history satisfies the model.
+    pub fn verify(&self, sessions: &[Session]) -> Option<Consistency> {
+        let history = match atomic_history(sessions) {
+            Ok(history) => history,
+            // allowed by read committed only
+            Err(Anomaly::NonRepeatableRead { .. }) => {
+                return match read_committed_history(sessions) {
+                    Ok(history) if check_read_committed(&history) => {
+                        Some(Consistency::ReadAtomic).filter(|&level| self.consistency_model.implies(level))
+                    }
+                    _ => Some(Consistency::ReadCommitted),
+                };
+            }
+            Err(_) => return Some(Consistency::ReadCommitted),
+        };
+
+        Consistency::levels()
+            .iter()
+            .filter(|&&level| self.consistency_model.implies(level))
+            .find(|&&level| !Self::check(&history, level))
+            .copied()
+    }
+
+    /// Checks the history against every level of `Consistency::levels()` in turn until one fails,
+    /// returning the strongest level satisfied and the weakest violated; either is `None` if there
+    /// is no such level.
+    pub fn classify(sessions: &[Session]) -> (Option<Consistency>, Option<Consistency>) {
+        let history = match atomic_history(sessions) {
+            Ok(history) => history,
+            Err(Anomaly::NonRepeatableRead { .. }) => {
+                return match read_committed_history(sessions) {
+                    Ok(history) if check_read_committed(&history) => {
+                        (Some(Consistency::ReadCommitted), Some(Consistency::ReadAtomic))
+                    }
+                    _ => (None, Some(Consistency::ReadCommitted)),
+                };
+            }
+            Err(_) => return (None, Some(Consistency::ReadCommitted)),
+        };
+
+        let levels = Consistency::levels();
+        match levels.iter().position(|&level| !Self::check(&history, level)) {
+            Some(i) => (i.checked_sub(1).map(|held| levels[held]), Some(levels[i])),
+            None => (levels.last().copied(), None),
+        }
+    }
+
+    /// The anomaly or the shortest cycle of dependencies by which the history violates `level`.
+    /// `None` if the history satisfies `level`, or if every cycle depends on an order of writers
+    /// which is not forced by the history.
+    pub fn counterexample(sessions: &[Session], level: Consistency) -> Option<Counterexample> {
+        let history = match atomic_history(sessions) {
+            Ok(history) => history,
+            Err(Anomaly::NonRepeatableRead { .. }) if level == Consistency::ReadCommitted => {
+                match read_committed_history(sessions) {
+                    Ok(history) => history,
+                    Err(anomaly) => return Some(Counterexample::Anomaly(anomaly)),
+                }
+            }
+            Err(anomaly) => return Some(Counterexample::Anomaly(anomaly)),
+        };
+
+        let cycle = find_cycle(&history, level)?;
+        let steps = cycle
+            .iter()
+            .enumerate()
+            .map(|(i, &(id, dependency))| {
+                let txn = history.txn(id);
+                let events = if id.0 == 0 {
+                    // the dependencies on the previous and the next transaction of the cycle
+                    let previous = cycle[(i + cycle.len() - 1) % cycle.len()].1;
+                    let mut variables: Vec<usize> = [previous, dependency].iter().filter_map(Dependency::variable).collect();
+                    variables.dedup();
+                    variables
+                        .into_iter()
+                        .map(|variable| Event {
+                            write: true,
+                            variable,
+                            value: 0,
+                            success: true,
+                        })
+                        .collect()
+                } else {
+                    sessions[id.0 - 1][txn.index].events.clone()
+                };
+                Step {
+                    session: id.0,
+                    index: txn.index,
+                    events,
+                    dependency,
+                }
+            })
+            .collect();
+
+        Some(Counterexample::Cycle(steps))
+    }
+
+    pub fn check(history: &AtomicHistory, level: Consistency) -> bool {
+        match level {
+            Consistency::ReadCommitted => check_read_committed(history),
+            Consistency::ReadAtomic => check_read_atomic(history),
+            Consistency::Causal => check_causal(history),
+            Consistency::Prefix => check_prefix(history),
+            Consistency::ParallelSnapshotIsolation => check_parallel_snapshot_isolation(history),
+            Consistency::SnapshotIsolation => check_snapshot_isolation(history),
+            Consistency::Serializable => check_serializable(history),
+            Consistency::StrictSerializable => check_strict_serializable(history),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::test_util::{read, txn, write};
+
+    fn passes(sessions: &[Session], level: Consistency) -> bool {
+        Verifier::new(level).verify(sessions).is_none()
+    }
+
+    #[test]
+    fn lost_update() {
+        // both increment x from 0
+        let sessions = vec![
+            vec![txn(vec![read(0, 0), write(0, 1)])],
+            vec![txn(vec![read(0, 0), write(0, 2)])],
+        ];
+        assert!(passes(&sessions, Consistency::Prefix));
+        assert!(!passes(&sessions, Consistency::ParallelSnapshotIsolation));
+        assert!(!passes(&sessions, Consistency::SnapshotIsolation));
+        assert!(!passes(&sessions, Consistency::Serializable));
+        assert_eq!(
+            Verifier::classify(&sessions),
+            (Some(Consistency::Prefix), Some(Consistency::ParallelSnapshotIsolation))
+        );
+    }
+
+    #[test]
+    fn write_skew() {
+        // both read x and y, then write the one the other did not
+        let sessions = vec![
+            vec![txn(vec![read(0, 0), read(1, 0), write(0, 1)])],
+            vec![txn(vec![read(0, 0), read(1, 0), write(1, 1)])],
+        ];
+        assert!(passes(&sessions, Consistency::SnapshotIsolation));
+        assert!(!passes(&sessions, Consistency::Serializable));
+        assert_eq!(
+            Verifier::classify(&sessions),
+            (Some(Consistency::SnapshotIsolation), Some(Consistency::Serializable))
+        );
+    }
+
+    #[test]
+    fn long_fork() {
+        // the readers observe the two independent writes in opposite orders
+        let sessions = vec![
+            vec![txn(vec![write(0, 1)])],
+            vec![txn(vec![write(1, 1)])],
+            vec![txn(vec![read(0, 1), read(1, 0)])],
+            vec![txn(vec![read(0, 0), read(1, 1)])],
+        ];
+        assert!(passes(&sessions, Consistency::ParallelSnapshotIsolation));
+        assert!(!passes(&sessions, Consistency::Prefix));
+        assert_eq!(Verifier::new(Consistency::Prefix).verify(&sessions), Some(Consistency::Prefix));
+    }
+
+    #[test]
+    fn fractured_read() {
+        // reads x before and y after the transaction writing both
+        let sessions = vec![
+            vec![txn(vec![write(0, 1), write(1, 1)])],
+            vec![txn(vec![read(0, 0), read(1, 1)])],
+        ];
+        assert!(passes(&sessions, Consistency::ReadCommitted));
+        assert!(!passes(&sessions, Consistency::ReadAtomic));
+        assert_eq!(
+            Verifier::classify(&sessions),
+            (Some(Consistency::ReadCommitted), Some(Consistency::ReadAtomic))
+        );
+    }
+
+    #[test]
+    fn non_monotonic_read() {
+        // reads y of the transaction writing both, then an older x
+        let sessions = vec![
+            vec![txn(vec![write(0, 1), write(1, 1)])],
+            vec![txn(vec![read(1, 1), read(0, 0)])],
+        ];
+        assert!(passes(&sessions, Consistency::ReadCommitted));
+        assert!(!passes(&sessions, Consistency::ReadAtomic));
+        assert_eq!(
+            Verifier::classify(&sessions),
+            (Some(Consistency::ReadCommitted), Some(Consistency::ReadAtomic))
+        );
+    }
+}
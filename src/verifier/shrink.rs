@@ -0,0 +1,251 @@
+//! Delta debugging of failing histories: sessions, then transactions, then events are removed in
+//! ever smaller chunks as long as the rest still fails, until no single one can be removed.
+//!
+//! Removing a write removes the reads of its value as well, unless another write of the value is
+//! left, such as the committed retry of an aborted attempt, so every read of what is left still
+//! reads a value written in it.
+
+use std::collections::HashSet;
+
+use crate::db::history::{Event, Session, Transaction};
+
+/// What is removed at once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Unit {
+    Session,
+    Transaction,
+    Event,
+}
+
+impl Unit {
+    /// The unit containing event `e` of transaction `t` of session `s`.
+    fn of(self, s: usize, t: usize, e: usize) -> (usize, usize, usize) {
+        match self {
+            Unit::Session => (s, 0, 0),
+            Unit::Transaction => (s, t, 0),
+            Unit::Event => (s, t, e),
+        }
+    }
+
+    /// Every unit of `sessions` in order. An empty session or transaction is a unit of its own,
+    /// as if it had a single event.
+    fn all(self, sessions: &[Session]) -> Vec<(usize, usize, usize)> {
+        let mut units = Vec::new();
+        for (s, session) in sessions.iter().enumerate() {
+            if session.is_empty() && self == Unit::Session {
+                units.push((s, 0, 0));
+            }
+            for (t, transaction) in session.iter().enumerate() {
+                let n_event = match self {
+                    Unit::Event => transaction.events.len(),
+                    _ => transaction.events.len().max(1),
+                };
+                for e in 0..n_event {
+                    let unit = self.of(s, t, e);
+                    if units.last() != Some(&unit) {
+                        units.push(unit);
+                    }
+                }
+            }
+        }
+        units
+    }
+}
+
+/// Shrinks `sessions` while `fails` holds for them, which it must for `sessions` themselves.
+pub fn shrink<F>(sessions: &[Session], mut fails: F) -> Vec<Session>
+where
+    F: FnMut(&[Session]) -> bool,
+{
+    let mut current = sessions.to_vec();
+
+    loop {
+        let mut changed = false;
+        for &unit in [Unit::Session, Unit::Transaction, Unit::Event].iter() {
+            let units = unit.all(&current);
+            // the sessions without the units removed last
+            let mut smaller = None;
+            reduce(units.len(), |removed| {
+                let removed: HashSet<_> = units.iter().zip(removed).filter(|(_, &r)| r).map(|(u, _)| *u).collect();
+                let candidate = without(&current, |s, t, e| removed.contains(&unit.of(s, t, e)));
+                let failing = fails(&candidate);
+                if failing {
+                    smaller = Some(candidate);
+                }
+                failing
+            });
+            if let Some(smaller) = smaller {
+                current = smaller;
+                changed = true;
+            }
+        }
+        if !changed {
+            return current;
+        }
+    }
+}
+
+/// Removes chunks of `n_unit` units, halving their size down to single units, as long as `fails`
+/// holds without them. Returns which units are removed.
+fn reduce<F>(n_unit: usize, mut fails: F) -> Vec<bool>
+where
+    F: FnMut(&[bool]) -> bool,
+{
+    let mut removed = vec![false; n_unit];
+    let mut size = n_unit.div_ceil(2);
+
+    while size > 0 {
+        let mut progress = false;
+        for start in (0..n_unit).step_by(size) {
+            let chunk = start..(start + size).min(n_unit);
+            if removed[chunk.clone()].iter().all(|&r| r) {
+                continue;
+            }
+            let mut candidate = removed.clone();
+            candidate[chunk].iter_mut().for_each(|r| *r = true);
+            if fails(&candidate) {
+                removed = candidate;
+                progress = true;
+            }
+        }
+        // single units are retried until none can be removed
+        if !progress || size > 1 {
+            size /= 2;
+        }
+    }
+
+    removed
+}
+
+/// The sessions without the events `removed(session, transaction, event)` and the reads of the
+/// values only they write, whether their transactions committed or not; transactions and sessions
+/// which are left empty by that are dropped. An empty transaction or session is dropped if its
+/// event `0` is removed.
+fn without<R>(sessions: &[Session], removed: R) -> Vec<Session>
+where
+    R: Fn(usize, usize, usize) -> bool,
+{
+    let mut lost = HashSet::new();
+    let mut kept = HashSet::new();
+    for (s, session) in sessions.iter().enumerate() {
+        for (t, transaction) in session.iter().enumerate() {
+            for (e, event) in transaction.events.iter().enumerate().filter(|(_, event)| event.write) {
+                if removed(s, t, e) {
+                    lost.insert((event.variable, event.value));
+                } else {
+                    kept.insert((event.variable, event.value));
+                }
+            }
+        }
+    }
+    lost.retain(|write| !kept.contains(write));
+
+    sessions
+        .iter()
+        .enumerate()
+        .filter_map(|(s, session)| {
+            let transactions: Session = session
+                .iter()
+                .enumerate()
+                .filter_map(|(t, transaction)| {
+                    let events: Vec<Event> = transaction
+                        .events
+                        .iter()
+                        .enumerate()
+                        .filter(|&(e, event)| {
+                            !removed(s, t, e) && (event.write || !lost.contains(&(event.variable, event.value)))
+                        })
+                        .map(|(_, event)| event.clone())
+                        .collect();
+                    let dropped = if transaction.events.is_empty() {
+                        removed(s, t, 0)
+                    } else {
+                        events.is_empty()
+                    };
+                    if dropped {
+                        None
+                    } else {
+                        Some(Transaction {
+                            events,
+                            ..transaction.clone()
+                        })
+                    }
+                })
+                .collect();
+            let dropped = if session.is_empty() {
+                removed(s, 0, 0)
+            } else {
+                transactions.is_empty()
+            };
+            if dropped {
+                None
+            } else {
+                Some(transactions)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consistency::Consistency;
+    use crate::verifier::test_util::{read, txn, write};
+    use crate::verifier::Verifier;
+
+    fn fails_ser(sessions: &[Session]) -> bool {
+        Verifier::new(Consistency::Serializable).verify(sessions).is_some()
+    }
+
+    #[test]
+    fn shrinks_padded_write_skew() {
+        let sessions = vec![
+            vec![
+                txn(vec![write(2, 1), read(3, 0)]),
+                // write skew with the first transaction of session 1
+                txn(vec![read(0, 0), read(1, 0), read(2, 1), write(0, 1)]),
+                txn(vec![read(0, 1), write(4, 1)]),
+            ],
+            vec![
+                txn(vec![read(0, 0), read(1, 0), write(1, 1), write(3, 1)]),
+                txn(vec![read(1, 1), read(4, 0)]),
+                txn(Vec::new()),
+            ],
+            vec![txn(vec![read(2, 1), write(2, 2)]), txn(vec![read(2, 2), read(3, 1)])],
+        ];
+        assert!(fails_ser(&sessions));
+        assert!(Verifier::new(Consistency::SnapshotIsolation).verify(&sessions).is_none());
+
+        let shrunk = shrink(&sessions, fails_ser);
+        assert!(fails_ser(&shrunk));
+
+        // 1-minimal: removing any single session, transaction or event, with the reads of what it
+        // writes, no longer fails
+        for unit in [Unit::Session, Unit::Transaction, Unit::Event] {
+            for removed in unit.all(&shrunk) {
+                let smaller = without(&shrunk, |s, t, e| unit.of(s, t, e) == removed);
+                assert!(!fails_ser(&smaller), "{:?} {:?} can be removed from {:?}", unit, removed, shrunk);
+            }
+        }
+        // a read and a write of each of the two transactions
+        assert_eq!(shrunk.iter().flatten().count(), 2);
+        assert_eq!(shrunk.iter().flatten().map(|t| t.events.len()).sum::<usize>(), 4);
+    }
+
+    #[test]
+    fn keeps_reads_of_a_value_written_by_what_is_left() {
+        let aborted = Transaction {
+            success: false,
+            ..txn(vec![write(0, 1)])
+        };
+        let sessions = vec![vec![aborted, txn(vec![write(0, 1)])], vec![txn(vec![read(0, 1)])]];
+
+        // the retry still writes what the aborted attempt wrote, and the other way round
+        for t in 0..2 {
+            let smaller = without(&sessions, |s, u, _| (s, u) == (0, t));
+            assert_eq!(smaller[1][0].events.len(), 1);
+        }
+        // without both the read goes, and so does everything
+        assert!(without(&sessions, |s, _, _| s == 0).is_empty());
+    }
+}
@@ -0,0 +1,30 @@
+//! Committed transactions of successful events, for the tests of the verifier and the shrinker.
+
+use crate::db::history::{Event, Transaction};
+
+pub fn read(variable: usize, value: usize) -> Event {
+    Event {
+        write: false,
+        variable,
+        value,
+        success: true,
+    }
+}
+
+pub fn write(variable: usize, value: usize) -> Event {
+    Event {
+        write: true,
+        variable,
+        value,
+        success: true,
+    }
+}
+
+pub fn txn(events: Vec<Event>) -> Transaction {
+    Transaction {
+        events,
+        success: true,
+        aborts: Vec::new(),
+        time: None,
+    }
+}